@@ -1,3 +1,19 @@
+import ctypes
+from functools import lru_cache
 from pathlib import Path
 
 LIB = Path(__file__).parent
+
+
+@lru_cache
+def load_library() -> ctypes.CDLL:
+    # The same file polars loads, so the statistics and warnings are the ones of its calls.
+    for suffix in (".so", ".pyd", ".dylib"):
+        for path in LIB.glob(f"*{suffix}"):
+            lib = ctypes.CDLL(str(path))
+            lib._polars_plugin_stats.restype = ctypes.c_char_p
+            lib._polars_plugin_allocator_stats.restype = ctypes.c_char_p
+            lib._polars_plugin_take_warnings.restype = ctypes.c_char_p
+            return lib
+    msg = f"no plugin library found in {LIB}"
+    raise FileNotFoundError(msg)
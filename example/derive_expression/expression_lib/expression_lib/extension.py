@@ -32,7 +32,7 @@ class Language:
         self._expr = expr
 
     def __getattr__(self, attr: str) -> Callable[..., pl.Expr]:
-        if attr in ("pig_latinnify", "append_args"):
+        if attr in ("pig_latinnify", "append_args", "word_stats"):
 
             def func(*args: Any, **kwargs: Any) -> pl.Expr:
                 return getattr(language, attr)(self._expr, *args, **kwargs)
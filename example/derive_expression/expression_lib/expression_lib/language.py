@@ -21,6 +21,18 @@ def pig_latinnify(expr: IntoExprColumn, capitalize: bool = False) -> pl.Expr:
     )
 
 
+def word_stats(expr: IntoExprColumn) -> pl.Expr:
+    """
+    Returns a struct with the fields `n_chars`, `n_vowels` and `pig_latin`.
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[expr],
+        function_name="word_stats",
+        is_elementwise=True,
+    )
+
+
 def append_args(
     expr: IntoExprColumn,
     float_arg: float,
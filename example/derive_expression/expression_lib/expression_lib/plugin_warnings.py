@@ -0,0 +1,15 @@
+from __future__ import annotations
+
+import json
+import warnings
+
+import polars as pl
+
+from expression_lib._utils import load_library
+
+
+def emit_plugin_warnings() -> None:
+    """Emit the warnings the plugin buffered during its calls."""
+    for warning in json.loads(load_library()._polars_plugin_take_warnings()):
+        category = getattr(pl.exceptions, warning["kind"], UserWarning)
+        warnings.warn(warning["message"], category, stacklevel=2)
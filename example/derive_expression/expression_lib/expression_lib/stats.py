@@ -0,0 +1,38 @@
+from __future__ import annotations
+
+import json
+
+import polars as pl
+
+from expression_lib._utils import load_library
+
+
+def plugin_stats() -> pl.DataFrame:
+    """Call statistics of the plugin's functions, one row per function."""
+    stats = json.loads(load_library()._polars_plugin_stats())
+    return pl.DataFrame(
+        stats,
+        schema={
+            "name": pl.String,
+            "calls": pl.UInt64,
+            "errors": pl.UInt64,
+            "rows_in": pl.UInt64,
+            "rows_out": pl.UInt64,
+            "wall_time_ns": pl.UInt64,
+        },
+    )
+
+
+def reset_plugin_stats() -> None:
+    """Reset the call statistics of the plugin's functions."""
+    load_library()._polars_plugin_stats_reset()
+
+
+def allocator_stats() -> dict[str, int]:
+    """Bytes and allocations of the plugin's `PolarsAllocator`."""
+    return json.loads(load_library()._polars_plugin_allocator_stats())
+
+
+def reset_allocator_stats() -> None:
+    """Reset the allocation counts and set the peak to the live bytes."""
+    load_library()._polars_plugin_allocator_stats_reset()
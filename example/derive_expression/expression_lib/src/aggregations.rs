@@ -0,0 +1,36 @@
+use polars::prelude::*;
+use pyo3_polars::derive::{polars_aggregation, Aggregation};
+
+/// The geometric mean, aggregated from the sum of the logarithms and the count. Null values are
+/// ignored.
+#[polars_aggregation(output_type=Float64, args=[expr])]
+struct GeometricMean;
+
+impl Aggregation for GeometricMean {
+    type Kwargs = ();
+    type State = (f64, u64);
+
+    fn init(_kwargs: &()) -> PolarsResult<Self::State> {
+        Ok((0.0, 0))
+    }
+
+    fn update(state: &mut Self::State, inputs: &[Series]) -> PolarsResult<()> {
+        let s = inputs[0].cast(&DataType::Float64)?;
+        for value in s.f64()?.iter().flatten() {
+            state.0 += value.ln();
+            state.1 += 1;
+        }
+        Ok(())
+    }
+
+    fn combine(state: &mut Self::State, other: Self::State) -> PolarsResult<()> {
+        state.0 += other.0;
+        state.1 += other.1;
+        Ok(())
+    }
+
+    fn finalize(state: Self::State) -> PolarsResult<Series> {
+        let mean = (state.1 > 0).then(|| (state.0 / state.1 as f64).exp());
+        Ok(Series::new("".into(), [mean]))
+    }
+}
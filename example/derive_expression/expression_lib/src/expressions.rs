@@ -1,6 +1,7 @@
+use polars::export::num::Float;
 use polars::prelude::*;
 use polars_plan::dsl::FieldsMapper;
-use pyo3_polars::derive::{polars_expr, CallerContext};
+use pyo3_polars::derive::{polars_expr, CallerContext, KwargsSchema, StructOutput};
 use pyo3_polars::export::polars_core::POOL;
 use serde::Deserialize;
 use std::fmt::Write;
@@ -24,7 +25,7 @@ fn pig_latin_str(value: &str, capitalize: bool, output: &mut String) {
     }
 }
 
-#[polars_expr(output_type=String)]
+#[polars_expr(output_type=String, is_elementwise=true, args=[expr])]
 fn pig_latinnify(inputs: &[Series], kwargs: PigLatinKwargs) -> PolarsResult<Series> {
     let ca = inputs[0].str()?;
     let out: StringChunked = ca.apply_into_string_amortized(|value, output| {
@@ -54,7 +55,7 @@ fn split_offsets(len: usize, n: usize) -> Vec<(usize, usize)> {
 }
 
 /// This expression will run in parallel if the `context` allows it.
-#[polars_expr(output_type=String)]
+#[polars_expr(output_type=String, is_elementwise=true, args=[expr])]
 fn pig_latinnify_with_paralellism(
     inputs: &[Series],
     context: CallerContext,
@@ -92,14 +93,14 @@ fn pig_latinnify_with_paralellism(
     }
 }
 
-#[polars_expr(output_type=Float64)]
+#[polars_expr(output_type=Float64, is_elementwise=true, args=[expr, other])]
 fn jaccard_similarity(inputs: &[Series]) -> PolarsResult<Series> {
     let a = inputs[0].list()?;
     let b = inputs[1].list()?;
     crate::distances::naive_jaccard_sim(a, b).map(|ca| ca.into_series())
 }
 
-#[polars_expr(output_type=Float64)]
+#[polars_expr(output_type=Float64, is_elementwise=true, args=[expr, other])]
 fn hamming_distance(inputs: &[Series]) -> PolarsResult<Series> {
     let a = inputs[0].str()?;
     let b = inputs[1].str()?;
@@ -112,34 +113,28 @@ fn haversine_output(input_fields: &[Field]) -> PolarsResult<Field> {
     FieldsMapper::new(input_fields).map_to_float_dtype()
 }
 
-#[polars_expr(output_type_func=haversine_output)]
-fn haversine(inputs: &[Series]) -> PolarsResult<Series> {
-    let out = match inputs[0].dtype() {
-        DataType::Float32 => {
-            let start_lat = inputs[0].f32().unwrap();
-            let start_long = inputs[1].f32().unwrap();
-            let end_lat = inputs[2].f32().unwrap();
-            let end_long = inputs[3].f32().unwrap();
-            crate::distances::naive_haversine(start_lat, start_long, end_lat, end_long)?
-                .into_series()
-        }
-        DataType::Float64 => {
-            let start_lat = inputs[0].f64().unwrap();
-            let start_long = inputs[1].f64().unwrap();
-            let end_lat = inputs[2].f64().unwrap();
-            let end_long = inputs[3].f64().unwrap();
-            crate::distances::naive_haversine(start_lat, start_long, end_lat, end_long)?
-                .into_series()
-        }
-        _ => unimplemented!(),
-    };
-    Ok(out)
+#[polars_expr(
+    output_type_func=haversine_output,
+    dispatch=[Float32, Float64],
+    cast_to=Float64,
+    is_elementwise=true,
+    cast_to_supertype=true,
+    args=[start_lat, start_long, end_lat, end_long]
+)]
+fn haversine<T>(inputs: &[&ChunkedArray<T>]) -> PolarsResult<Series>
+where
+    T: PolarsFloatType,
+    T::Native: Float,
+{
+    let out = crate::distances::naive_haversine(inputs[0], inputs[1], inputs[2], inputs[3])?;
+    Ok(out.into_series())
 }
 
 /// The `DefaultKwargs` isn't very ergonomic as it doesn't validate any schema.
 /// Provide your own kwargs struct with the proper schema and accept that type
-/// in your plugin expression.
-#[derive(Deserialize)]
+/// in your plugin expression. Deriving `KwargsSchema` makes parse errors name
+/// the kwarg that is missing or has the wrong type.
+#[derive(Deserialize, KwargsSchema)]
 pub struct MyKwargs {
     float_arg: f64,
     integer_arg: i64,
@@ -150,7 +145,7 @@ pub struct MyKwargs {
 /// If you want to accept `kwargs`. You define a `kwargs` argument
 /// on the second position in you plugin. You can provide any custom struct that is deserializable
 /// with the pickle protocol (on the rust side).
-#[polars_expr(output_type=String)]
+#[polars_expr(output_type=String, is_elementwise=true, args=[expr])]
 fn append_kwargs(input: &[Series], kwargs: MyKwargs) -> PolarsResult<Series> {
     let input = &input[0];
     let input = input.cast(&DataType::String)?;
@@ -168,7 +163,34 @@ fn append_kwargs(input: &[Series], kwargs: MyKwargs) -> PolarsResult<Series> {
         .into_series())
 }
 
-#[polars_expr(output_type=Boolean)]
+#[derive(StructOutput)]
+struct WordStats {
+    n_chars: UInt32Chunked,
+    n_vowels: UInt32Chunked,
+    #[struct_output(rename = "pig_latin")]
+    pig_latinnified: StringChunked,
+}
+
+/// Several outputs computed in one pass are returned as a `Struct`. The output
+/// type follows from `WordStats`, so no `output_type` is needed.
+#[polars_expr(is_elementwise=true, args=[expr])]
+fn word_stats(inputs: &[Series]) -> PolarsResult<WordStats> {
+    let ca = inputs[0].str()?;
+    let n_chars =
+        ca.apply_nonnull_values_generic(DataType::UInt32, |value| value.chars().count() as u32);
+    let n_vowels = ca.apply_nonnull_values_generic(DataType::UInt32, |value| {
+        value.chars().filter(|c| "aeiouAEIOU".contains(*c)).count() as u32
+    });
+    let pig_latinnified =
+        ca.apply_into_string_amortized(|value, output| pig_latin_str(value, false, output));
+    Ok(WordStats {
+        n_chars,
+        n_vowels,
+        pig_latinnified,
+    })
+}
+
+#[polars_expr(output_type=Boolean, is_elementwise=true, args=[expr])]
 fn is_leap_year(input: &[Series]) -> PolarsResult<Series> {
     let input = &input[0];
     let ca = input.date()?;
@@ -181,7 +203,7 @@ fn is_leap_year(input: &[Series]) -> PolarsResult<Series> {
     Ok(out.into_series())
 }
 
-#[polars_expr(output_type=Boolean)]
+#[polars_expr(output_type=Boolean, args=[expr])]
 fn panic(_input: &[Series]) -> PolarsResult<Series> {
     todo!()
 }
@@ -200,7 +222,7 @@ fn convert_timezone(input_fields: &[Field], kwargs: TimeZone) -> PolarsResult<Fi
 
 /// This expression is for demonstration purposes as we have a dedicated
 /// `convert_time_zone` in Polars.
-#[polars_expr(output_type_func_with_kwargs=convert_timezone)]
+#[polars_expr(output_type_func_with_kwargs=convert_timezone, is_elementwise=true, args=[expr])]
 fn change_time_zone(input: &[Series], kwargs: TimeZone) -> PolarsResult<Series> {
     let input = &input[0];
     let ca = input.datetime()?;
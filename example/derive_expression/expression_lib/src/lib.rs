@@ -1,5 +1,6 @@
 use pyo3_polars::PolarsAllocator;
 
+mod aggregations;
 mod distances;
 mod expressions;
 
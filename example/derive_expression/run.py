@@ -37,6 +37,13 @@ out = df.with_columns(
 
 print(out)
 
+# Struct outputs can be unnested into their fields.
+out = df.select(language.word_stats("names")).unnest("names")
+assert out.columns == ["n_chars", "n_vowels", "pig_latin"]
+assert out["n_chars"].to_list() == [7, 5, 3]
+assert out["n_vowels"].to_list() == [2, 3, 1]
+print(out)
+
 # Test we can extend the expressions by importing the extension module.
 
 import expression_lib.extension  # noqa: F401
@@ -60,6 +67,33 @@ out = df.with_columns(
 
 print(out)
 
+# The same functions, from the module generated by `build.rs`.
+from expression_lib import _plugins
+
+out = df.with_columns(
+    pig_latin=_plugins.pig_latinnify("names", capitalize=False),
+    hamming_dist=_plugins.hamming_distance("names", "moons"),
+    haversine=_plugins.haversine("start_lat", "start_lon", "end_lat", "end_lon"),
+    appended_args=_plugins.append_kwargs(
+        "names",
+        float_arg=11.234,
+        integer_arg=93,
+        boolean_arg=False,
+        string_arg="example",
+    ),
+)
+
+print(out)
+
+# Aggregations return one value per group.
+out = (
+    pl.DataFrame({"group": ["a", "a", "b"], "value": [1.0, 4.0, 3.0]})
+    .group_by("group", maintain_order=True)
+    .agg(_plugins.geometric_mean("value"))
+)
+assert [round(v, 6) for v in out["value"]] == [2.0, 3.0]
+print(out)
+
 
 # Tests we can return errors from FFI by passing wrong types.
 try:
@@ -73,11 +107,37 @@ try:
     )
 except pl.exceptions.ComputeError as e:
     assert "the plugin failed with message" in str(e)
+    # `MyKwargs` derives `KwargsSchema`, so the error names the kwarg and its type.
+    assert "invalid kwarg `float_arg` (`f64`)" in str(e)
 
 
 try:
     out.with_columns(pl.col("names").panic.panic())
 except pl.exceptions.ComputeError as e:
-    assert "the plugin panicked" in str(e)
+    # The panic message and location are returned to Python.
+    assert "panicked at" in str(e)
+    assert "not yet implemented" in str(e)
+
+# The plugin is built with the `stats` feature, so it counts its calls.
+from expression_lib.stats import (
+    allocator_stats,
+    plugin_stats,
+    reset_allocator_stats,
+    reset_plugin_stats,
+)
+
+stats = plugin_stats()
+print(stats)
+assert stats.filter(pl.col("name") == "pig_latinnify")["calls"].item() >= 1
+reset_plugin_stats()
+assert plugin_stats()["calls"].sum() == 0
+
+# The plugin allocates through the `PolarsAllocator`, which counts its allocations.
+reset_allocator_stats()
+df.select(language.pig_latinnify("names"))
+alloc = allocator_stats()
+print(alloc)
+assert alloc["allocations"] > 0
+assert alloc["peak_bytes"] >= alloc["live_bytes"]
 
 print("finished")
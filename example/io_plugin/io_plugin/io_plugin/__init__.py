@@ -1,4 +1,4 @@
-from .io_plugin import new_bernoulli, new_uniform, RandomSource
+from .io_plugin import new_bernoulli, new_uniform, RandomSource, flush_logs, set_log_level
 from typing import Any, Iterator
 from polars.io.plugins import register_io_source
 import polars as pl
@@ -113,6 +113,7 @@ impl RandomSource {
 
 #[pymodule]
 fn io_plugin(m: &Bound<PyModule>) -> PyResult<()> {
+    pyo3_polars::logging::register(m)?;
     m.add_class::<RandomSource>().unwrap();
     m.add_class::<PySampler>().unwrap();
     m.add_wrapped(wrap_pyfunction!(samplers::new_bernoulli))
@@ -0,0 +1,95 @@
+//! `polars-plugin-inspect <PLUGIN> list|field|call`
+//!
+//! Loads a compiled expression plugin and calls its symbols on the columns of an Arrow IPC or
+//! Parquet file.
+use std::fs::File;
+use std::path::Path;
+use std::process::ExitCode;
+
+use polars::prelude::*;
+use pyo3_polars::derive::{serialize_kwargs, KwargsEncoding};
+use pyo3_polars::loader::Plugin;
+
+const USAGE: &str = "usage: polars-plugin-inspect <PLUGIN> list
+       polars-plugin-inspect <PLUGIN> field <NAME> <INPUT> [--kwargs <JSON>]
+       polars-plugin-inspect <PLUGIN> call <NAME> <INPUT> [--kwargs <JSON>]";
+
+fn read_input(path: &str) -> PolarsResult<DataFrame> {
+    let file = File::open(path)?;
+    match Path::new(path).extension().and_then(|ext| ext.to_str()) {
+        Some("parquet") => ParquetReader::new(file).finish(),
+        Some("arrow" | "ipc" | "feather") => IpcReader::new(file).finish(),
+        _ => {
+            polars_bail!(InvalidOperation: "unsupported input {}, expected an Arrow IPC or Parquet file", path)
+        }
+    }
+}
+
+fn read_kwargs(args: &[String]) -> PolarsResult<Vec<u8>> {
+    match args {
+        [] => Ok(vec![]),
+        [flag, json] if flag == "--kwargs" => {
+            let kwargs: serde_json::Value = serde_json::from_str(json)
+                .map_err(|err| polars_err!(InvalidOperation: "invalid --kwargs: {}", err))?;
+            serialize_kwargs(&kwargs, KwargsEncoding::Pickle)
+        }
+        _ => polars_bail!(InvalidOperation: "{}", USAGE),
+    }
+}
+
+fn list(plugin: &Plugin) {
+    let (major, minor) = plugin.version();
+    println!("{} (FFI version {major}.{minor})", plugin.path().display());
+    println!("\nexpressions:");
+    for name in plugin.expressions() {
+        println!("  {name}");
+    }
+    println!("\nsymbols:");
+    for symbol in plugin.symbols() {
+        println!("  {symbol}");
+    }
+}
+
+fn run(args: &[String]) -> PolarsResult<()> {
+    let [path, command, rest @ ..] = args else {
+        polars_bail!(InvalidOperation: "{}", USAGE);
+    };
+    let plugin = unsafe { Plugin::load(path)? };
+
+    match (command.as_str(), rest) {
+        ("list", []) => list(&plugin),
+        ("field", [name, input, kwargs @ ..]) => {
+            let df = read_input(input)?;
+            let fields = df
+                .get_columns()
+                .iter()
+                .map(|column| column.field().into_owned())
+                .collect::<Vec<_>>();
+            let field = plugin.field(name, &fields, &read_kwargs(kwargs)?)?;
+            println!("{}: {}", field.name(), field.dtype());
+        }
+        ("call", [name, input, kwargs @ ..]) => {
+            let df = read_input(input)?;
+            let inputs = df
+                .get_columns()
+                .iter()
+                .map(|column| column.as_materialized_series().clone())
+                .collect::<Vec<_>>();
+            let out = plugin.call(name, &inputs, &read_kwargs(kwargs)?, Default::default())?;
+            println!("{out}");
+        }
+        _ => polars_bail!(InvalidOperation: "{}", USAGE),
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
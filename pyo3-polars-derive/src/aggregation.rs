@@ -0,0 +1,164 @@
+use crate::attr::ExprsFunctionOptions;
+use crate::{
+    create_field_function, create_field_function_from_with_dtype, get_expression_function_name,
+    insert_error_function, quote_enter_call, quote_process_results, quote_record_inputs,
+    quote_start_stats,
+};
+use quote::quote;
+
+/// `GeometricMean` -> `geometric_mean`
+fn snake_case(ident: &syn::Ident) -> String {
+    let mut out = String::new();
+    for (i, c) in ident.to_string().chars().enumerate() {
+        if c.is_uppercase() {
+            if i > 0 {
+                out.push('_');
+            }
+            out.extend(c.to_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// A closure getting the kwargs of the aggregation `ty` from `kwargs_ptr` and `kwargs_len`, see
+/// `pyo3_polars::derive::_AggregationKwargs`.
+fn quote_get_kwargs(ty: &syn::Ident) -> proc_macro2::TokenStream {
+    quote!(
+        || {
+            use pyo3_polars::derive::{
+                _GetAggregationKwargs as _, _GetAggregationKwargsWithSchema as _,
+                _GetAggregationState as _, _GetAggregationStateWithSchema as _,
+            };
+            let kwargs = std::slice::from_raw_parts(kwargs_ptr, kwargs_len);
+            let getter = pyo3_polars::derive::_aggregation_kwargs::<#ty>();
+            (&&&&getter).get_kwargs(kwargs)
+        }
+    )
+}
+
+fn agg_function_name(kind: &str, name: &syn::Ident) -> syn::Ident {
+    syn::Ident::new(&format!("_polars_plugin_agg_{kind}_{name}"), name.span())
+}
+
+pub(crate) fn create_aggregation(
+    item: syn::ItemStruct,
+    options: ExprsFunctionOptions,
+) -> proc_macro2::TokenStream {
+    let ty = &item.ident;
+    if options.dispatch.is_some() || options.cast_to.is_some() {
+        panic!("`dispatch` is only supported on polars_expr")
+    }
+    if options.memory_budget.is_some() {
+        panic!("`memory_budget` is only supported on polars_expr")
+    }
+    let name = options
+        .name
+        .unwrap_or_else(|| syn::Ident::new(&snake_case(ty), ty.span()));
+
+    let expanded_field_fn =
+        if let Some(fn_name) = options.output_type_fn.or(options.output_type_fn_kwargs) {
+            create_field_function(&name, quote!(#fn_name))
+        } else if let Some(dtype) = options.output_dtype {
+            create_field_function_from_with_dtype(&name, dtype)
+        } else {
+            panic!("didn't understand polars_aggregation attribute")
+        };
+
+    let error_msg_fn = insert_error_function();
+    let quote_process_result = quote_process_results(false);
+    let start_stats = quote_start_stats(&name);
+    let record_inputs = quote_record_inputs();
+    let enter_call = quote_enter_call();
+    let get_kwargs = quote_get_kwargs(ty);
+    let expr_fn_name = get_expression_function_name(&name);
+    let init_fn_name = agg_function_name("init", &name);
+    let update_fn_name = agg_function_name("update", &name);
+    let combine_fn_name = agg_function_name("combine", &name);
+    let finalize_fn_name = agg_function_name("finalize", &name);
+    let drop_fn_name = agg_function_name("drop", &name);
+
+    quote!(
+        #item
+
+        use pyo3_polars::export::*;
+
+        #error_msg_fn
+
+        #expanded_field_fn
+
+        // The eager expression aggregates the whole group at once.
+        #[no_mangle]
+        pub unsafe extern "C" fn #expr_fn_name (
+            e: *mut polars_ffi::version_0::SeriesExport,
+            input_len: usize,
+            kwargs_ptr: *const u8,
+            kwargs_len: usize,
+            return_value: *mut polars_ffi::version_0::SeriesExport,
+            _context: *mut polars_ffi::version_0::CallerContext
+        )  {
+            let panic_result = std::panic::catch_unwind(move || {
+                #enter_call
+                let inputs = match pyo3_polars::derive::_import_inputs(e, input_len) {
+                    Ok(inputs) => inputs,
+                    Err(err) => {
+                        pyo3_polars::derive::_update_last_error(err);
+                        return;
+                    }
+                };
+
+                #start_stats
+                #record_inputs
+
+                let result = pyo3_polars::derive::_aggregate::<#ty>(&inputs, #get_kwargs);
+
+                #quote_process_result
+            });
+
+            if let Err(payload) = panic_result {
+                // Set latest to panic;
+                pyo3_polars::derive::_set_panic_payload(&*payload);
+            }
+        }
+
+        // The partial states, see `pyo3_polars::derive::Aggregation`.
+        #[no_mangle]
+        pub unsafe extern "C" fn #init_fn_name(
+            kwargs_ptr: *const u8,
+            kwargs_len: usize,
+        ) -> *mut std::ffi::c_void {
+            pyo3_polars::derive::_aggregation_init::<#ty>(#get_kwargs)
+        }
+
+        #[no_mangle]
+        pub unsafe extern "C" fn #update_fn_name(
+            state: *mut std::ffi::c_void,
+            e: *mut polars_ffi::version_0::SeriesExport,
+            input_len: usize,
+        ) -> bool {
+            pyo3_polars::derive::_aggregation_update::<#ty>(state, e, input_len)
+        }
+
+        #[no_mangle]
+        pub unsafe extern "C" fn #combine_fn_name(
+            state: *mut std::ffi::c_void,
+            other: *mut std::ffi::c_void,
+        ) -> bool {
+            pyo3_polars::derive::_aggregation_combine::<#ty>(state, other)
+        }
+
+        #[no_mangle]
+        pub unsafe extern "C" fn #finalize_fn_name(
+            state: *mut std::ffi::c_void,
+            return_value: *mut polars_ffi::version_0::SeriesExport,
+        ) -> bool {
+            pyo3_polars::derive::_aggregation_finalize::<#ty>(state, return_value)
+        }
+
+        #[no_mangle]
+        pub unsafe extern "C" fn #drop_fn_name(state: *mut std::ffi::c_void) {
+            pyo3_polars::derive::_aggregation_drop::<#ty>(state)
+        }
+    )
+}
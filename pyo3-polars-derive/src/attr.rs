@@ -2,7 +2,8 @@ use crate::keywords;
 use proc_macro2::Ident;
 use std::fmt::Debug;
 use syn::parse::{Parse, ParseStream};
-use syn::Token;
+use syn::punctuated::Punctuated;
+use syn::{bracketed, Token};
 
 #[derive(Clone, Debug)]
 pub struct KeyWordAttribute<K, V> {
@@ -24,12 +25,39 @@ pub type OutputAttribute = KeyWordAttribute<keywords::output_type, Ident>;
 pub type OutputFuncAttribute = KeyWordAttribute<keywords::output_type_func, Ident>;
 pub type OutputFuncAttributeWithKwargs =
     KeyWordAttribute<keywords::output_type_func_with_kwargs, Ident>;
+pub type NameAttribute = KeyWordAttribute<keywords::name, Ident>;
+pub type IsElementwiseAttribute = KeyWordAttribute<keywords::is_elementwise, syn::LitBool>;
+pub type ReturnsScalarAttribute = KeyWordAttribute<keywords::returns_scalar, syn::LitBool>;
+pub type ChangesLengthAttribute = KeyWordAttribute<keywords::changes_length, syn::LitBool>;
+pub type CastToSupertypeAttribute = KeyWordAttribute<keywords::cast_to_supertype, syn::LitBool>;
+pub type ArgsAttribute = KeyWordAttribute<keywords::args, IdentList>;
+pub type NamespaceAttribute = KeyWordAttribute<keywords::namespace, Ident>;
+pub type DispatchAttribute = KeyWordAttribute<keywords::dispatch, IdentList>;
+pub type CastToAttribute = KeyWordAttribute<keywords::cast_to, Ident>;
+pub type MemoryBudgetAttribute = KeyWordAttribute<keywords::memory_budget, syn::Expr>;
+
+/// A bracketed list of identifiers, e.g. `[start_lat, start_long]`.
+#[derive(Clone, Debug)]
+pub struct IdentList(pub Vec<Ident>);
+
+impl Parse for IdentList {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let content;
+        bracketed!(content in input);
+        let idents = Punctuated::<Ident, Token![,]>::parse_terminated(&content)?;
+        Ok(IdentList(idents.into_iter().collect()))
+    }
+}
 
 #[derive(Default, Debug)]
 pub struct ExprsFunctionOptions {
     pub output_dtype: Option<Ident>,
     pub output_type_fn: Option<Ident>,
     pub output_type_fn_kwargs: Option<Ident>,
+    pub name: Option<Ident>,
+    pub dispatch: Option<Vec<Ident>>,
+    pub cast_to: Option<Ident>,
+    pub memory_budget: Option<syn::Expr>,
 }
 
 impl Parse for ExprsFunctionOptions {
@@ -48,9 +76,72 @@ impl Parse for ExprsFunctionOptions {
             } else if lookahead.peek(keywords::output_type_func_with_kwargs) {
                 let attr = input.parse::<OutputFuncAttributeWithKwargs>()?;
                 options.output_type_fn_kwargs = Some(attr.value)
+            } else if lookahead.peek(keywords::name) {
+                let attr = input.parse::<NameAttribute>()?;
+                options.name = Some(attr.value)
+            } else if lookahead.peek(keywords::dispatch) {
+                let attr = input.parse::<DispatchAttribute>()?;
+                options.dispatch = Some(attr.value.0)
+            } else if lookahead.peek(keywords::cast_to) {
+                let attr = input.parse::<CastToAttribute>()?;
+                options.cast_to = Some(attr.value)
+            } else if lookahead.peek(keywords::memory_budget) {
+                let attr = input.parse::<MemoryBudgetAttribute>()?;
+                options.memory_budget = Some(attr.value)
+            } else if lookahead.peek(keywords::is_elementwise) {
+                // The Python-facing attributes only describe the registration on the Python
+                // side. They are read by `pyo3-polars-stubgen` and don't change the expansion.
+                input.parse::<IsElementwiseAttribute>()?;
+            } else if lookahead.peek(keywords::returns_scalar) {
+                input.parse::<ReturnsScalarAttribute>()?;
+            } else if lookahead.peek(keywords::changes_length) {
+                input.parse::<ChangesLengthAttribute>()?;
+            } else if lookahead.peek(keywords::cast_to_supertype) {
+                input.parse::<CastToSupertypeAttribute>()?;
+            } else if lookahead.peek(keywords::args) {
+                input.parse::<ArgsAttribute>()?;
+            } else if lookahead.peek(keywords::namespace) {
+                input.parse::<NamespaceAttribute>()?;
             } else {
                 panic!("didn't recognize attribute")
             }
+            if !input.is_empty() {
+                let _: Token![,] = input.parse()?;
+            }
+        }
+        Ok(options)
+    }
+}
+
+pub type DtypeAttribute = KeyWordAttribute<keywords::dtype, Ident>;
+pub type RenameAttribute = KeyWordAttribute<keywords::rename, syn::LitStr>;
+
+/// The options of a `#[struct_output(..)]` field attribute.
+#[derive(Default, Debug)]
+pub struct StructOutputFieldOptions {
+    pub dtype: Option<Ident>,
+    pub rename: Option<syn::LitStr>,
+}
+
+impl Parse for StructOutputFieldOptions {
+    fn parse(input: ParseStream<'_>) -> syn::Result<Self> {
+        let mut options = StructOutputFieldOptions::default();
+
+        while !input.is_empty() {
+            let lookahead = input.lookahead1();
+
+            if lookahead.peek(keywords::dtype) {
+                let attr = input.parse::<DtypeAttribute>()?;
+                options.dtype = Some(attr.value)
+            } else if lookahead.peek(keywords::rename) {
+                let attr = input.parse::<RenameAttribute>()?;
+                options.rename = Some(attr.value)
+            } else {
+                return Err(lookahead.error());
+            }
+            if !input.is_empty() {
+                let _: Token![,] = input.parse()?;
+            }
         }
         Ok(options)
     }
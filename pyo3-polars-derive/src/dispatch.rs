@@ -0,0 +1,55 @@
+use quote::{format_ident, quote};
+
+/// The dtypes a generic expression function is instantiated with, from
+/// `#[polars_expr(dispatch = [..], cast_to = ..)]`.
+pub(crate) struct Dispatch {
+    pub(crate) dtypes: Vec<syn::Ident>,
+    pub(crate) cast_to: Option<syn::Ident>,
+}
+
+/// `Float64` -> `polars_core::datatypes::Float64Type`
+fn polars_type(dtype: &syn::Ident) -> proc_macro2::TokenStream {
+    let ty = format_ident!("{}Type", dtype);
+    quote!(polars_core::datatypes::#ty)
+}
+
+/// Calls `fn_name(&inputs, args..)`, instantiated for the dtype of the inputs if dispatched.
+pub(crate) fn quote_call_fn(
+    fn_name: &syn::Ident,
+    dispatch: Option<&Dispatch>,
+    args: &[proc_macro2::TokenStream],
+) -> proc_macro2::TokenStream {
+    let Some(dispatch) = dispatch else {
+        return quote!(#fn_name(&inputs #(, #args)*));
+    };
+
+    let name = fn_name.to_string();
+    let dtypes = &dispatch.dtypes;
+    let cast_to = match &dispatch.cast_to {
+        Some(dtype) => quote!(Some(polars_core::datatypes::DataType::#dtype)),
+        None => quote!(None),
+    };
+    let arms = dtypes.iter().map(|dtype| {
+        let ty = polars_type(dtype);
+        quote!(
+            Ok((polars_core::datatypes::DataType::#dtype, inputs)) => {
+                match pyo3_polars::derive::_downcast_inputs::<#ty>(&inputs) {
+                    Ok(inputs) => #fn_name::<#ty>(&inputs #(, #args)*),
+                    Err(err) => Err(err),
+                }
+            }
+        )
+    });
+    quote!(
+        match pyo3_polars::derive::_dispatch_inputs(
+            #name,
+            &inputs,
+            &[#(polars_core::datatypes::DataType::#dtypes),*],
+            #cast_to,
+        ) {
+            #(#arms)*
+            Ok((dtype, _)) => unreachable!("dispatched to unlisted dtype {}", dtype),
+            Err(err) => Err(err),
+        }
+    )
+}
@@ -1,3 +1,20 @@
 syn::custom_keyword!(output_type);
 syn::custom_keyword!(output_type_func);
 syn::custom_keyword!(output_type_func_with_kwargs);
+// The dtypes a generic `#[polars_expr]` is instantiated with.
+syn::custom_keyword!(dispatch);
+syn::custom_keyword!(cast_to);
+// The function name of a `#[polars_aggregation]`.
+syn::custom_keyword!(name);
+// The memory budget of a `#[polars_expr]`, needs the `memory-budget` feature of pyo3-polars.
+syn::custom_keyword!(memory_budget);
+// Python-facing metadata, consumed by `pyo3-polars-stubgen`.
+syn::custom_keyword!(is_elementwise);
+syn::custom_keyword!(returns_scalar);
+syn::custom_keyword!(changes_length);
+syn::custom_keyword!(cast_to_supertype);
+syn::custom_keyword!(args);
+syn::custom_keyword!(namespace);
+// Options of `#[struct_output(..)]` on the fields of a `#[derive(StructOutput)]`.
+syn::custom_keyword!(dtype);
+syn::custom_keyword!(rename);
@@ -0,0 +1,120 @@
+use quote::quote;
+use syn::Token;
+
+#[derive(Default)]
+struct SerdeAttrs {
+    default: bool,
+    rename: Option<String>,
+}
+
+fn serde_attrs(attrs: &[syn::Attribute]) -> syn::Result<SerdeAttrs> {
+    let mut out = SerdeAttrs::default();
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("serde")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("default") {
+                out.default = true;
+                if meta.input.peek(Token![=]) {
+                    meta.value()?.parse::<syn::LitStr>()?;
+                }
+            } else if meta.path.is_ident("rename") && meta.input.peek(Token![=]) {
+                out.rename = Some(meta.value()?.parse::<syn::LitStr>()?.value());
+            } else if meta.input.peek(Token![=]) {
+                // Other serde attributes are left to serde.
+                meta.value()?.parse::<syn::Expr>()?;
+            } else if meta.input.peek(syn::token::Paren) {
+                let content;
+                syn::parenthesized!(content in meta.input);
+                content.parse::<proc_macro2::TokenStream>()?;
+            }
+            Ok(())
+        })?;
+    }
+    Ok(out)
+}
+
+fn doc(attrs: &[syn::Attribute]) -> String {
+    attrs
+        .iter()
+        .filter_map(|attr| match &attr.meta {
+            syn::Meta::NameValue(meta) if meta.path.is_ident("doc") => match &meta.value {
+                syn::Expr::Lit(syn::ExprLit {
+                    lit: syn::Lit::Str(doc),
+                    ..
+                }) => Some(doc.value().trim().to_string()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// `Vec < String >` -> `Vec<String>`
+fn type_name(ty: &syn::Type) -> String {
+    quote!(#ty)
+        .to_string()
+        .replace(" :: ", "::")
+        .replace(" <", "<")
+        .replace("< ", "<")
+        .replace(" >", ">")
+        .replace(" ,", ",")
+        .replace("& ", "&")
+}
+
+fn is_option(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "Option"),
+        _ => false,
+    }
+}
+
+pub(crate) fn derive_kwargs_schema(
+    input: syn::DeriveInput,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let name = &input.ident;
+    let fields = match &input.data {
+        syn::Data::Struct(syn::DataStruct {
+            fields: syn::Fields::Named(fields),
+            ..
+        }) => &fields.named,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input,
+                "KwargsSchema can only be derived for structs with named fields",
+            ))
+        }
+    };
+
+    let container = serde_attrs(&input.attrs)?;
+    let mut kwarg_fields = Vec::with_capacity(fields.len());
+    for field in fields {
+        let serde = serde_attrs(&field.attrs)?;
+        let kwarg = serde
+            .rename
+            .unwrap_or_else(|| field.ident.as_ref().unwrap().to_string());
+        let rust_type = type_name(&field.ty);
+        let description = doc(&field.attrs);
+        let has_default = container.default || serde.default || is_option(&field.ty);
+        kwarg_fields.push(quote!(
+            pyo3_polars::derive::KwargField {
+                name: #kwarg,
+                rust_type: #rust_type,
+                description: #description,
+                has_default: #has_default,
+            }
+        ));
+    }
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote!(
+        impl #impl_generics pyo3_polars::derive::KwargsSchema for #name #ty_generics #where_clause {
+            fn kwargs_fields() -> &'static [pyo3_polars::derive::KwargField] {
+                &[#(#kwarg_fields),*]
+            }
+        }
+    ))
+}
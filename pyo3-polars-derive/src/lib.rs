@@ -1,6 +1,12 @@
+mod aggregation;
 mod attr;
+mod dispatch;
 mod keywords;
+mod kwargs_schema;
+mod plugin_init;
+mod struct_output;
 
+use dispatch::{quote_call_fn, Dispatch};
 use proc_macro::TokenStream;
 use quote::quote;
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -14,21 +20,30 @@ fn insert_error_function() -> proc_macro2::TokenStream {
     // Only expose the error retrieval function on the first expression.
     if !is_init {
         quote!(
-            pub use pyo3_polars::derive::_polars_plugin_get_last_error_message;
+            pub use pyo3_polars::derive::{
+                _polars_plugin_clear_last_error, _polars_plugin_get_last_error_context,
+                _polars_plugin_get_last_error_kind, _polars_plugin_get_last_error_message,
+            };
         )
     } else {
         proc_macro2::TokenStream::new()
     }
 }
 
-fn quote_get_kwargs() -> proc_macro2::TokenStream {
+/// `parser` is a `pyo3_polars::derive::_KwargsParser` of the kwargs type. Kwargs types that
+/// implement `KwargsSchema` get errors describing the expected kwargs.
+fn quote_get_kwargs(parser: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
     quote!(
     let kwargs = std::slice::from_raw_parts(kwargs_ptr, kwargs_len);
 
-    let kwargs = match pyo3_polars::derive::_parse_kwargs(kwargs)  {
+    let kwargs = {
+        use pyo3_polars::derive::{_ParseKwargs as _, _ParseKwargsWithSchema as _};
+        (&#parser).parse_kwargs(kwargs)
+    };
+    let kwargs = match kwargs  {
         Ok(value) => value,
         Err(err) => {
-            let err = polars_err!(InvalidOperation: "could not parse kwargs: '{}'\n\nCheck: registration of kwargs in the plugin.", err);
+            let err = polars_core::error::polars_err!(InvalidOperation: "could not parse kwargs: '{}'\n\nCheck: registration of kwargs in the plugin.", err);
             pyo3_polars::derive::_update_last_error(err);
             return;
         }
@@ -37,66 +52,249 @@ fn quote_get_kwargs() -> proc_macro2::TokenStream {
     )
 }
 
-fn quote_call_kwargs(ast: &syn::ItemFn, fn_name: &syn::Ident) -> proc_macro2::TokenStream {
-    let kwargs = quote_get_kwargs();
-    quote!(
-            // parse the kwargs and assign to `let kwargs`
-            #kwargs
+/// The parameters of an expression function after the inputs: scalar arguments, then optionally
+/// `context` and `kwargs`, or `state` built from the kwargs.
+struct Params {
+    scalars: Vec<(syn::Ident, syn::Type)>,
+    context: bool,
+    kwargs: Option<syn::Type>,
+    /// The `PluginState` type of `state: &T`.
+    state: Option<syn::Type>,
+}
 
-            // define the function
-            #ast
+fn expression_params(ast: &syn::ItemFn) -> Params {
+    let mut args = ast
+        .sig
+        .inputs
+        .iter()
+        .skip(1)
+        .map(|fn_arg| {
+            if let FnArg::Typed(pat) = fn_arg {
+                if let syn::Pat::Ident(ident) = pat.pat.as_ref() {
+                    (ident.ident.clone(), pat.ty.as_ref().clone())
+                } else {
+                    panic!("expected an argument")
+                }
+            } else {
+                panic!("expected a type argument")
+            }
+        })
+        .collect::<Vec<_>>();
 
-            // call the function
-        let result: PolarsResult<polars_core::prelude::Series> = #fn_name(&inputs, kwargs);
+    let kwargs = match args.last() {
+        Some((ident, _)) if ident == "kwargs" => args.pop().map(|(_, ty)| ty),
+        _ => None,
+    };
+    let state = match args.last() {
+        Some((ident, _)) if ident == "state" && kwargs.is_none() => match args.pop() {
+            Some((_, syn::Type::Reference(ty))) => Some(ty.elem.as_ref().clone()),
+            _ => panic!("`state` must be a reference, e.g. `state: &MyState`"),
+        },
+        _ => None,
+    };
+    let context = matches!(args.last(), Some((ident, _)) if ident == "context");
+    if context {
+        args.pop();
+    }
+    for (ident, _) in &args {
+        if ident == "kwargs" && context {
+            panic!("'kwargs', 'context' order should be reversed")
+        } else if ident == "kwargs" || ident == "context" || ident == "state" {
+            panic!("'{}' should come after the scalar arguments", ident)
+        }
+    }
+    Params {
+        scalars: args,
+        context,
+        kwargs,
+        state,
+    }
+}
 
+/// Gets the cached `PluginState` of the kwargs, see `pyo3_polars::derive::PluginState`.
+fn quote_get_state(ty: &syn::Type) -> proc_macro2::TokenStream {
+    quote!(
+        let kwargs = std::slice::from_raw_parts(kwargs_ptr, kwargs_len);
+        let state = pyo3_polars::derive::_cached_state::<#ty>(kwargs, |kwargs| {
+            use pyo3_polars::derive::{_ParseKwargs as _, _ParseKwargsWithSchema as _};
+            let parser = pyo3_polars::derive::_kwargs_parser::<
+                <#ty as pyo3_polars::derive::PluginState>::Kwargs
+            >();
+            (&parser).parse_kwargs(kwargs).map_err(|err| {
+                polars_core::error::polars_err!(InvalidOperation: "could not parse kwargs: '{}'\n\nCheck: registration of kwargs in the plugin.", err)
+            })
+        });
+        let state = match state {
+            Ok(state) => state,
+            Err(err) => {
+                pyo3_polars::derive::_update_last_error(err);
+                return;
+            }
+        };
     )
 }
 
-fn quote_call_context(ast: &syn::ItemFn, fn_name: &syn::Ident) -> proc_macro2::TokenStream {
+/// Runs the `#[polars_plugin_init]` functions before the first call.
+fn quote_plugin_init() -> proc_macro2::TokenStream {
+    quote!(if let Err(err) = pyo3_polars::derive::_plugin_init() {
+        pyo3_polars::derive::_update_last_error(err);
+        return;
+    })
+}
+
+/// Marks the thread as running a plugin call, so that panics are handled by the panic hook of the
+/// plugin, see `pyo3_polars::derive::disable_panic_hook`.
+fn quote_enter_call() -> proc_macro2::TokenStream {
+    quote!(let _plugin_call = pyo3_polars::derive::_PluginCall::enter();)
+}
+
+/// Starts `call_timer`, which records the call statistics with the `stats` feature of
+/// pyo3-polars. It is finished in `quote_process_results`.
+fn quote_start_stats(name: &syn::Ident) -> proc_macro2::TokenStream {
+    let name = name.to_string();
     quote!(
-            let context = *context;
+        static CALL_STATS: pyo3_polars::derive::_CallStats = pyo3_polars::derive::_CallStats::new(#name);
+        let call_timer = CALL_STATS.start();
+    )
+}
 
-            // define the function
-            #ast
+/// Records the inputs in the statistics started by `quote_start_stats`, after the scalar arguments
+/// are split off.
+fn quote_record_inputs() -> proc_macro2::TokenStream {
+    quote!(call_timer.record_inputs(&inputs);)
+}
 
-            // call the function
-        let result: PolarsResult<polars_core::prelude::Series> = #fn_name(&inputs, context);
+/// Extracts the scalar arguments from the last inputs, leaving the other inputs in `inputs`.
+fn quote_get_scalars(scalars: &[(syn::Ident, syn::Type)]) -> proc_macro2::TokenStream {
+    if scalars.is_empty() {
+        return proc_macro2::TokenStream::new();
+    }
+    let n_scalars = scalars.len();
+    let get_scalars = scalars.iter().enumerate().map(|(i, (ident, ty))| {
+        let name = ident.to_string();
+        quote!(
+            let #ident: #ty = match pyo3_polars::derive::_scalar_arg(#name, &scalar_inputs[#i]) {
+                Ok(value) => value,
+                Err(err) => {
+                    pyo3_polars::derive::_update_last_error(err);
+                    return;
+                }
+            };
+        )
+    });
+    quote!(
+        let (inputs, scalar_inputs) = match pyo3_polars::derive::_split_scalar_args(&inputs, #n_scalars) {
+            Ok(split) => split,
+            Err(err) => {
+                pyo3_polars::derive::_update_last_error(err);
+                return;
+            }
+        };
+        #(#get_scalars)*
     )
 }
 
-fn quote_call_context_kwargs(ast: &syn::ItemFn, fn_name: &syn::Ident) -> proc_macro2::TokenStream {
+fn quote_call(
+    ast: &syn::ItemFn,
+    fn_name: &syn::Ident,
+    dispatch: Option<&Dispatch>,
+    memory_budget: Option<&syn::Expr>,
+    params: &Params,
+) -> proc_macro2::TokenStream {
+    let get_scalars = quote_get_scalars(&params.scalars);
+    let mut args = params
+        .scalars
+        .iter()
+        .map(|(ident, _)| quote!(#ident))
+        .collect::<Vec<_>>();
+
+    let get_context = if params.context {
+        args.push(quote!(context));
+        quote!(let context = *context;)
+    } else {
+        proc_macro2::TokenStream::new()
+    };
+    let get_kwargs = if let Some(ty) = &params.kwargs {
+        args.push(quote!(kwargs));
+        quote_get_kwargs(quote!(pyo3_polars::derive::_kwargs_parser::<#ty>()))
+    } else {
+        proc_macro2::TokenStream::new()
+    };
+
+    let get_state = if let Some(ty) = &params.state {
+        args.push(quote!(&state));
+        quote_get_state(ty)
+    } else {
+        proc_macro2::TokenStream::new()
+    };
+
+    let call = quote_call_fn(fn_name, dispatch, &args);
+    let call = match memory_budget {
+        Some(bytes) => quote!(pyo3_polars::with_memory_budget(#bytes, || #call)),
+        None => call,
+    };
+    let record_inputs = quote_record_inputs();
     quote!(
-            let context = *context;
+            #get_scalars
 
-            let kwargs = std::slice::from_raw_parts(kwargs_ptr, kwargs_len);
+            #record_inputs
 
-            let kwargs = match pyo3_polars::derive::_parse_kwargs(kwargs)  {
-                    Ok(value) => value,
-                    Err(err) => {
-                        pyo3_polars::derive::_update_last_error(err);
-                        return;
-                    }
-            };
+            #get_context
+
+            // parse the kwargs and assign to `let kwargs`
+            #get_kwargs
+
+            #get_state
 
             // define the function
             #ast
 
             // call the function
-        let result: PolarsResult<polars_core::prelude::Series> = #fn_name(&inputs, context, kwargs);
+        let result = #call;
     )
 }
 
-fn quote_call_no_kwargs(ast: &syn::ItemFn, fn_name: &syn::Ident) -> proc_macro2::TokenStream {
-    quote!(
-            // define the function
-            #ast
-            // call the function
-            let result: PolarsResult<polars_core::prelude::Series> = #fn_name(&inputs);
-    )
+/// The type `T` of a function returning `PolarsResult<T>` if it is not a `Series`, in which case
+/// the output is returned as a `Struct`.
+fn struct_output_type(ast: &syn::ItemFn) -> Option<syn::Type> {
+    let syn::ReturnType::Type(_, ty) = &ast.sig.output else {
+        return None;
+    };
+    let syn::Type::Path(path) = ty.as_ref() else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    let syn::PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    match args.args.first()? {
+        syn::GenericArgument::Type(syn::Type::Path(inner))
+            if inner
+                .path
+                .segments
+                .last()
+                .is_some_and(|segment| segment.ident == "Series") =>
+        {
+            None
+        }
+        syn::GenericArgument::Type(inner) => Some(inner.clone()),
+        _ => None,
+    }
 }
 
-fn quote_process_results() -> proc_macro2::TokenStream {
-    quote!(match result {
+fn quote_process_results(struct_output: bool) -> proc_macro2::TokenStream {
+    let convert = if struct_output {
+        quote!(
+            let result = result.and_then(|out| pyo3_polars::derive::_into_struct_series(out, &inputs));
+        )
+    } else {
+        proc_macro2::TokenStream::new()
+    };
+    quote!(
+    #convert
+    let result: PolarsResult<polars_core::prelude::Series> = result;
+    call_timer.finish(&result);
+    match result {
         Ok(out) => {
             // Update return value.
             *return_value = polars_ffi::version_0::export_series(&out);
@@ -108,46 +306,28 @@ fn quote_process_results() -> proc_macro2::TokenStream {
     })
 }
 
-fn create_expression_function(ast: syn::ItemFn) -> proc_macro2::TokenStream {
-    // count how often the user define a kwargs argument.
-    let args = ast
-        .sig
-        .inputs
-        .iter()
-        .skip(1)
-        .map(|fn_arg| {
-            if let FnArg::Typed(pat) = fn_arg {
-                if let syn::Pat::Ident(pat) = pat.pat.as_ref() {
-                    pat.ident.to_string()
-                } else {
-                    panic!("expected an argument")
-                }
-            } else {
-                panic!("expected a type argument")
-            }
-        })
-        .collect::<Vec<_>>();
-
+fn create_expression_function(
+    ast: syn::ItemFn,
+    dispatch: Option<Dispatch>,
+    memory_budget: Option<syn::Expr>,
+) -> proc_macro2::TokenStream {
+    let params = expression_params(&ast);
     let fn_name = &ast.sig.ident;
     let error_msg_fn = insert_error_function();
 
     // Get the tokenstream of the call logic.
-    let quote_call = match args.len() {
-        0 => quote_call_no_kwargs(&ast, fn_name),
-        1 => match args[0].as_str() {
-            "kwargs" => quote_call_kwargs(&ast, fn_name),
-            "context" => quote_call_context(&ast, fn_name),
-            a => panic!("didn't expect argument {}", a),
-        },
-        2 => match (args[0].as_str(), args[1].as_str()) {
-            ("context", "kwargs") => quote_call_context_kwargs(&ast, fn_name),
-            ("kwargs", "context") => panic!("'kwargs', 'context' order should be reversed"),
-            (a, b) => panic!("didn't expect arguments {}, {}", a, b),
-        },
-        _ => panic!("didn't expect so many arguments"),
-    };
+    let quote_call = quote_call(
+        &ast,
+        fn_name,
+        dispatch.as_ref(),
+        memory_budget.as_ref(),
+        &params,
+    );
 
-    let quote_process_result = quote_process_results();
+    let quote_process_result = quote_process_results(struct_output_type(&ast).is_some());
+    let start_stats = quote_start_stats(fn_name);
+    let enter_call = quote_enter_call();
+    let plugin_init = quote_plugin_init();
     let fn_name = get_expression_function_name(fn_name);
 
     quote!(
@@ -166,16 +346,27 @@ fn create_expression_function(ast: syn::ItemFn) -> proc_macro2::TokenStream {
             context: *mut polars_ffi::version_0::CallerContext
         )  {
             let panic_result = std::panic::catch_unwind(move || {
-                let inputs = polars_ffi::version_0::import_series_buffer(e, input_len).unwrap();
+                #enter_call
+                let inputs = match pyo3_polars::derive::_import_inputs(e, input_len) {
+                    Ok(inputs) => inputs,
+                    Err(err) => {
+                        pyo3_polars::derive::_update_last_error(err);
+                        return;
+                    }
+                };
+
+                #start_stats
+
+                #plugin_init
 
                 #quote_call
 
                 #quote_process_result
             });
 
-            if panic_result.is_err() {
+            if let Err(payload) = panic_result {
                 // Set latest to panic;
-                pyo3_polars::derive::_set_panic();
+                pyo3_polars::derive::_set_panic_payload(&*payload);
             }
 
         }
@@ -189,40 +380,61 @@ fn get_field_function_name(fn_name: &syn::Ident) -> syn::Ident {
     )
 }
 
+fn get_context_field_function_name(fn_name: &syn::Ident) -> syn::Ident {
+    syn::Ident::new(
+        &format!("_polars_plugin_context_field_{}", fn_name),
+        fn_name.span(),
+    )
+}
+
 fn get_expression_function_name(fn_name: &syn::Ident) -> syn::Ident {
     syn::Ident::new(&format!("_polars_plugin_{}", fn_name), fn_name.span())
 }
 
 fn quote_get_inputs() -> proc_macro2::TokenStream {
     quote!(
-             let inputs = std::slice::from_raw_parts(field, len);
-             let inputs = inputs.iter().map(|field| {
-                 let field = polars_core::export::arrow::ffi::import_field_from_c(field).unwrap();
-                 let out = polars_core::prelude::Field::from(&field);
-                 out
-             }).collect::<Vec<_>>();
+             let inputs = pyo3_polars::derive::_import_fields(field, len);
+             let inputs = match inputs {
+                 Ok(inputs) => inputs,
+                 Err(err) => {
+                     pyo3_polars::derive::_update_last_error(err);
+                     return;
+                 }
+             };
     )
 }
 
+/// `dtype_fn_name` takes the input fields and optionally a `context` and kwargs, see
+/// `pyo3_polars::derive::_OutputTypeFunc`.
+///
+/// Polars calls the field function without a context, so besides `_polars_plugin_field_<name>`,
+/// which passes the default context, `_polars_plugin_context_field_<name>` takes the context for
+/// hosts that have it.
 fn create_field_function(
     fn_name: &syn::Ident,
-    dtype_fn_name: &syn::Ident,
-    kwargs: bool,
+    dtype_fn_name: proc_macro2::TokenStream,
 ) -> proc_macro2::TokenStream {
     let map_field_name = get_field_function_name(fn_name);
+    let context_field_name = get_context_field_function_name(fn_name);
     let inputs = quote_get_inputs();
-
-    let call_fn = if kwargs {
-        let kwargs = quote_get_kwargs();
-        quote! (
-            #kwargs
-            let result = #dtype_fn_name(&inputs, kwargs);
-        )
-    } else {
-        quote!(
-            let result = #dtype_fn_name(&inputs);
-        )
-    };
+    let plugin_init = quote_plugin_init();
+    let enter_call = quote_enter_call();
+
+    let call_fn = quote!(
+        let parser = pyo3_polars::derive::_output_type_kwargs_parser(&#dtype_fn_name);
+        let context = if context.is_null() {
+            polars_ffi::version_0::CallerContext::default()
+        } else {
+            *context
+        };
+        let result = pyo3_polars::derive::_call_output_type(&#dtype_fn_name, &inputs, context, || {
+            use pyo3_polars::derive::{_ParseKwargs as _, _ParseKwargsWithSchema as _};
+            let kwargs = std::slice::from_raw_parts(kwargs_ptr, kwargs_len);
+            (&parser).parse_kwargs(kwargs).map_err(|err| {
+                polars_core::error::polars_err!(InvalidOperation: "could not parse kwargs: '{}'\n\nCheck: registration of kwargs in the plugin.", err)
+            })
+        });
+    );
 
     quote! (
         #[no_mangle]
@@ -232,10 +444,27 @@ fn create_field_function(
             return_value: *mut polars_core::export::arrow::ffi::ArrowSchema,
             kwargs_ptr: *const u8,
             kwargs_len: usize,
+        ) {
+            // Polars doesn't pass a context to the field function.
+            let mut context = polars_ffi::version_0::CallerContext::default();
+            #context_field_name(field, len, return_value, kwargs_ptr, kwargs_len, &mut context)
+        }
+
+        #[no_mangle]
+        pub unsafe extern "C" fn #context_field_name(
+            field: *mut polars_core::export::arrow::ffi::ArrowSchema,
+            len: usize,
+            return_value: *mut polars_core::export::arrow::ffi::ArrowSchema,
+            kwargs_ptr: *const u8,
+            kwargs_len: usize,
+            context: *mut polars_ffi::version_0::CallerContext,
         ) {
             let panic_result = std::panic::catch_unwind(move || {
+                #enter_call
                 #inputs;
 
+                #plugin_init
+
                 #call_fn;
 
                 match result {
@@ -250,9 +479,9 @@ fn create_field_function(
                 }
             });
 
-            if panic_result.is_err() {
+            if let Err(payload) = panic_result {
                 // Set latest to panic;
-                pyo3_polars::derive::_set_panic();
+                pyo3_polars::derive::_set_panic_payload(&*payload);
             }
         }
     )
@@ -264,21 +493,43 @@ fn create_field_function_from_with_dtype(
 ) -> proc_macro2::TokenStream {
     let map_field_name = get_field_function_name(fn_name);
     let inputs = quote_get_inputs();
+    let plugin_init = quote_plugin_init();
+    let enter_call = quote_enter_call();
 
+    // The kwargs are not used, but the signature is the same as the other field functions so
+    // all of them can be called the same way.
     quote! (
         #[no_mangle]
         pub unsafe extern "C" fn #map_field_name(
             field: *mut polars_core::export::arrow::ffi::ArrowSchema,
             len: usize,
-            return_value: *mut polars_core::export::arrow::ffi::ArrowSchema
+            return_value: *mut polars_core::export::arrow::ffi::ArrowSchema,
+            _kwargs_ptr: *const u8,
+            _kwargs_len: usize,
         ) {
-            #inputs
+            let panic_result = std::panic::catch_unwind(move || {
+                #enter_call
+                #inputs
 
-            let mapper = polars_plan::dsl::FieldsMapper::new(&inputs);
-            let dtype = polars_core::datatypes::DataType::#dtype;
-            let out = mapper.with_dtype(dtype).unwrap();
-            let out = polars_core::export::arrow::ffi::export_field_to_c(&out.to_arrow(CompatLevel::newest()));
-            *return_value = out;
+                #plugin_init
+
+                let dtype = polars_core::datatypes::DataType::#dtype;
+                match pyo3_polars::derive::_output_field_with_dtype(&inputs, dtype) {
+                    Ok(out) => {
+                        let out = polars_core::export::arrow::ffi::export_field_to_c(&out.to_arrow(CompatLevel::newest()));
+                        *return_value = out;
+                    },
+                    Err(err) => {
+                        // Set latest error, but leave return value in empty state.
+                        pyo3_polars::derive::_update_last_error(err);
+                    }
+                }
+            });
+
+            if let Err(payload) = panic_result {
+                // Set latest to panic;
+                pyo3_polars::derive::_set_panic_payload(&*payload);
+            }
         }
     )
 }
@@ -288,17 +539,38 @@ pub fn polars_expr(attr: TokenStream, input: TokenStream) -> TokenStream {
     let ast = parse_macro_input!(input as syn::ItemFn);
 
     let options = parse_macro_input!(attr as attr::ExprsFunctionOptions);
-    let expanded_field_fn = if let Some(fn_name) = options.output_type_fn {
-        create_field_function(&ast.sig.ident, &fn_name, false)
-    } else if let Some(fn_name) = options.output_type_fn_kwargs {
-        create_field_function(&ast.sig.ident, &fn_name, true)
-    } else if let Some(dtype) = options.output_dtype {
-        create_field_function_from_with_dtype(&ast.sig.ident, dtype)
-    } else {
-        panic!("didn't understand polars_expr attribute")
+    if options.name.is_some() {
+        panic!("`name` is only supported on polars_aggregation")
+    }
+    let expanded_field_fn =
+        if let Some(fn_name) = options.output_type_fn.or(options.output_type_fn_kwargs) {
+            create_field_function(&ast.sig.ident, quote!(#fn_name))
+        } else if let Some(dtype) = options.output_dtype {
+            create_field_function_from_with_dtype(&ast.sig.ident, dtype)
+        } else if let Some(ty) = struct_output_type(&ast) {
+            if let Err(err) = struct_output::check_tuple_output(&ty) {
+                return err.into_compile_error().into();
+            }
+            // The struct fields follow from the output type.
+            let dtype_fn = quote!(pyo3_polars::derive::_struct_output_dtype::<#ty>);
+            create_field_function(&ast.sig.ident, dtype_fn)
+        } else {
+            panic!("didn't understand polars_expr attribute")
+        };
+
+    let dispatch = match (options.dispatch, options.cast_to) {
+        (Some(dtypes), cast_to) => {
+            if let Some(cast_to) = &cast_to {
+                if !dtypes.contains(cast_to) {
+                    panic!("`cast_to` must be one of the `dispatch` dtypes")
+                }
+            }
+            Some(Dispatch { dtypes, cast_to })
+        }
+        (None, Some(_)) => panic!("`cast_to` requires `dispatch`"),
+        (None, None) => None,
     };
-
-    let expanded_expr = create_expression_function(ast);
+    let expanded_expr = create_expression_function(ast, dispatch, options.memory_budget);
     let expanded = quote!(
         #expanded_field_fn
 
@@ -306,3 +578,46 @@ pub fn polars_expr(attr: TokenStream, input: TokenStream) -> TokenStream {
     );
     TokenStream::from(expanded)
 }
+
+/// Registers a type implementing `Aggregation` as an expression plugin.
+///
+/// The function name defaults to the type name in snake case and can be set with `name = ..`.
+/// The output type is given as on `#[polars_expr]`.
+#[proc_macro_attribute]
+pub fn polars_aggregation(attr: TokenStream, input: TokenStream) -> TokenStream {
+    let item = parse_macro_input!(input as syn::ItemStruct);
+    let options = parse_macro_input!(attr as attr::ExprsFunctionOptions);
+    TokenStream::from(aggregation::create_aggregation(item, options))
+}
+
+/// Runs the function once per process, before the first call of the plugin.
+///
+/// The function takes no arguments and returns `PolarsResult<()>`. If it fails, every call of
+/// the plugin fails with its error.
+#[proc_macro_attribute]
+pub fn polars_plugin_init(_attr: TokenStream, input: TokenStream) -> TokenStream {
+    let item = parse_macro_input!(input as syn::ItemFn);
+    TokenStream::from(plugin_init::create_plugin_init(item))
+}
+
+/// Implements `StructOutput` for a struct with named fields, so that it can be returned from a
+/// `#[polars_expr]` function as a `Struct` column.
+///
+/// Fields can be configured with `#[struct_output(dtype = Float64, rename = "name")]`.
+#[proc_macro_derive(StructOutput, attributes(struct_output))]
+pub fn struct_output(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as syn::DeriveInput);
+    struct_output::derive_struct_output(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Implements `KwargsSchema` for a kwargs struct, describing its fields from the serde attributes
+/// and doc comments.
+#[proc_macro_derive(KwargsSchema, attributes(serde))]
+pub fn kwargs_schema(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as syn::DeriveInput);
+    kwargs_schema::derive_kwargs_schema(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
@@ -0,0 +1,16 @@
+use quote::quote;
+
+/// Keeps the function and submits it to the `#[polars_plugin_init]` functions of the library.
+/// Submitting only links a static node into a list, the function itself is called before the
+/// first call of the plugin.
+pub(crate) fn create_plugin_init(item: syn::ItemFn) -> proc_macro2::TokenStream {
+    let fn_name = &item.sig.ident;
+
+    quote!(
+        #item
+
+        pyo3_polars::derive::inventory::submit! {
+            pyo3_polars::derive::_PluginInitHook(#fn_name)
+        }
+    )
+}
@@ -0,0 +1,139 @@
+use crate::attr::StructOutputFieldOptions;
+use quote::quote;
+
+pub(crate) fn derive_struct_output(
+    input: syn::DeriveInput,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let name = &input.ident;
+    let fields = match &input.data {
+        syn::Data::Struct(syn::DataStruct {
+            fields: syn::Fields::Named(fields),
+            ..
+        }) => &fields.named,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input,
+                "StructOutput can only be derived for structs with named fields",
+            ))
+        }
+    };
+    if fields.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input,
+            "StructOutput needs at least one field",
+        ));
+    }
+
+    let mut struct_fields = Vec::with_capacity(fields.len());
+    let mut columns = Vec::with_capacity(fields.len());
+    for field in fields {
+        let mut options = StructOutputFieldOptions::default();
+        for attr in field
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("struct_output"))
+        {
+            let parsed = attr.parse_args::<StructOutputFieldOptions>()?;
+            options.dtype = parsed.dtype.or(options.dtype);
+            options.rename = parsed.rename.or(options.rename);
+        }
+
+        let ident = field.ident.as_ref().unwrap();
+        let ty = &field.ty;
+        if options.dtype.is_none() && !has_static_dtype(ty) {
+            return Err(syn::Error::new_spanned(
+                ty,
+                "a `Series` or nested `ChunkedArray` member needs `#[struct_output(dtype = ..)]`, as its dtype isn't known before the call",
+            ));
+        }
+        let column_name = options
+            .rename
+            .map(|rename| rename.value())
+            .unwrap_or_else(|| ident.to_string());
+
+        let (dtype, cast) = match options.dtype {
+            Some(dtype) => (
+                quote!(Some(pyo3_polars::export::polars_core::datatypes::DataType::#dtype)),
+                quote!(.cast(&pyo3_polars::export::polars_core::datatypes::DataType::#dtype)?),
+            ),
+            None => (quote!(None), quote!()),
+        };
+
+        struct_fields.push(quote!(
+            pyo3_polars::derive::_struct_output_field::<#ty>(#column_name, #dtype, input_fields)?
+        ));
+        columns.push(quote!(
+            pyo3_polars::derive::StructOutputField::into_series(self.#ident)
+                .with_name(#column_name.into())
+                #cast
+        ));
+    }
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote!(
+        impl #impl_generics pyo3_polars::derive::StructOutput for #name #ty_generics #where_clause {
+            fn struct_fields(
+                input_fields: &[pyo3_polars::export::polars_core::prelude::Field],
+            ) -> pyo3_polars::export::polars_core::error::PolarsResult<
+                Vec<pyo3_polars::export::polars_core::prelude::Field>,
+            > {
+                Ok(vec![#(#struct_fields),*])
+            }
+
+            fn into_columns(
+                self,
+            ) -> pyo3_polars::export::polars_core::error::PolarsResult<
+                Vec<pyo3_polars::export::polars_core::prelude::Series>,
+            > {
+                Ok(vec![#(#columns),*])
+            }
+        }
+    ))
+}
+
+/// Checks that every member of a tuple output has a static dtype, as tuple members can't be
+/// given one.
+pub(crate) fn check_tuple_output(ty: &syn::Type) -> syn::Result<()> {
+    let syn::Type::Tuple(tuple) = ty else {
+        return Ok(());
+    };
+    match tuple.elems.iter().find(|elem| !has_static_dtype(elem)) {
+        Some(elem) => Err(syn::Error::new_spanned(
+            elem,
+            "a tuple output can't have `Series` or nested `ChunkedArray` members, as their dtype isn't known before the call; return a `#[derive(StructOutput)]` struct with `#[struct_output(dtype = ..)]` instead",
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Whether the dtype of `ty` is known before the call, i.e. it isn't a `Series` or a nested
+/// `ChunkedArray`, whose dtype is only known at runtime.
+fn has_static_dtype(ty: &syn::Type) -> bool {
+    const NESTED: &[&str] = &[
+        "ListChunked",
+        "ArrayChunked",
+        "StructChunked",
+        "ListType",
+        "FixedSizeListType",
+        "StructType",
+    ];
+    let syn::Type::Path(path) = ty else {
+        return true;
+    };
+    let Some(segment) = path.path.segments.last() else {
+        return true;
+    };
+    if segment.ident == "Series" || NESTED.iter().any(|name| segment.ident == name) {
+        return false;
+    }
+    // `ChunkedArray<ListType>` and the like.
+    match &segment.arguments {
+        syn::PathArguments::AngleBracketed(args) if segment.ident == "ChunkedArray" => {
+            args.args.iter().all(|arg| match arg {
+                syn::GenericArgument::Type(inner) => has_static_dtype(inner),
+                _ => true,
+            })
+        }
+        _ => true,
+    }
+}
@@ -0,0 +1,10 @@
+use polars_core::error::PolarsResult;
+use polars_core::prelude::{CompatLevel, Series};
+use pyo3_polars_derive::polars_expr;
+
+#[polars_expr(output_type=Float64, is_elementwise=true, cast_to_supertype=false, args=[expr, other], namespace=dist)]
+fn difference(series: &[Series]) -> PolarsResult<Series> {
+    &series[0] - &series[1]
+}
+
+fn main() {}
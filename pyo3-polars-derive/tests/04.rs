@@ -0,0 +1,34 @@
+use polars_core::error::PolarsResult;
+use polars_core::prelude::{
+    CompatLevel, DataType, Float64Chunked, IdxCa, IdxSize, NewChunkedArray, Series,
+};
+use pyo3_polars::derive::StructOutput;
+use pyo3_polars_derive::polars_expr;
+
+#[derive(StructOutput)]
+struct MinMax {
+    #[struct_output(dtype = Float64)]
+    min: Series,
+    #[struct_output(rename = "max_value", dtype = Float64)]
+    max: Series,
+    len: IdxCa,
+}
+
+#[polars_expr]
+fn min_max(series: &[Series]) -> PolarsResult<MinMax> {
+    let s = &series[0];
+    Ok(MinMax {
+        min: s.min_reduce()?.into_series(s.name().clone()),
+        max: s.max_reduce()?.into_series(s.name().clone()),
+        len: IdxCa::from_slice(s.name().clone(), &[s.len() as IdxSize]),
+    })
+}
+
+#[polars_expr(is_elementwise = true)]
+fn halves(series: &[Series]) -> PolarsResult<(Float64Chunked, Float64Chunked)> {
+    let s = series[0].cast(&DataType::Float64)?;
+    let halves = s.f64()? / 2.0;
+    Ok((halves, s.f64()?.clone()))
+}
+
+fn main() {}
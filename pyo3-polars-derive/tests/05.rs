@@ -0,0 +1,69 @@
+use polars_core::error::PolarsResult;
+use polars_core::prelude::{CompatLevel, Series};
+use pyo3_polars::derive::{polars_aggregation, Aggregation};
+use serde::Deserialize;
+
+#[polars_aggregation(output_type=Float64, returns_scalar=true, args=[expr])]
+struct SumOfSquares;
+
+impl Aggregation for SumOfSquares {
+    type Kwargs = ();
+    type State = f64;
+
+    fn init(_kwargs: &()) -> PolarsResult<f64> {
+        Ok(0.0)
+    }
+
+    fn update(state: &mut f64, inputs: &[Series]) -> PolarsResult<()> {
+        let ca = inputs[0].f64()?;
+        *state += ca.into_no_null_iter().map(|v| v * v).sum::<f64>();
+        Ok(())
+    }
+
+    fn combine(state: &mut f64, other: f64) -> PolarsResult<()> {
+        *state += other;
+        Ok(())
+    }
+
+    fn finalize(state: f64) -> PolarsResult<Series> {
+        Ok(Series::new("".into(), [state]))
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct TopKwargs {
+    k: usize,
+}
+
+#[polars_aggregation(output_type=Int64, name=top_k)]
+struct Top;
+
+impl Aggregation for Top {
+    type Kwargs = TopKwargs;
+    type State = (usize, Vec<i64>);
+
+    fn init(kwargs: &TopKwargs) -> PolarsResult<Self::State> {
+        Ok((kwargs.k, Vec::new()))
+    }
+
+    fn update(state: &mut Self::State, inputs: &[Series]) -> PolarsResult<()> {
+        state.1.extend(inputs[0].i64()?.into_no_null_iter());
+        Ok(())
+    }
+
+    fn combine(state: &mut Self::State, other: Self::State) -> PolarsResult<()> {
+        state.1.extend(other.1);
+        Ok(())
+    }
+
+    fn finalize(mut state: Self::State) -> PolarsResult<Series> {
+        state.1.sort_unstable_by(|a, b| b.cmp(a));
+        state.1.truncate(state.0);
+        Ok(Series::new("".into(), state.1))
+    }
+}
+
+fn main() {
+    let _ = _polars_plugin_top_k;
+    let _ = _polars_plugin_agg_combine_sum_of_squares;
+}
@@ -0,0 +1,24 @@
+use polars_core::error::PolarsResult;
+use polars_core::prelude::{ChunkedArray, CompatLevel, IntoSeries, PolarsNumericType, Series};
+use pyo3_polars::derive::CallerContext;
+use pyo3_polars_derive::polars_expr;
+
+#[polars_expr(output_type=Float64, dispatch=[Float32, Float64], cast_to=Float64)]
+fn sum_all<T: PolarsNumericType>(inputs: &[&ChunkedArray<T>]) -> PolarsResult<Series> {
+    let mut acc = inputs[0].clone();
+    for ca in &inputs[1..] {
+        acc = &acc + *ca;
+    }
+    Ok(acc.into_series())
+}
+
+#[polars_expr(output_type=Int64, dispatch=[Int32, Int64])]
+fn first_with_context<T: PolarsNumericType>(
+    inputs: &[&ChunkedArray<T>],
+    context: CallerContext,
+) -> PolarsResult<Series> {
+    let _ = context.parallel();
+    Ok(inputs[0].clone().into_series())
+}
+
+fn main() {}
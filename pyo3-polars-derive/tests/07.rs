@@ -0,0 +1,26 @@
+use polars_core::error::PolarsResult;
+use polars_core::prelude::{ChunkApply, CompatLevel, IntoSeries, Series};
+use pyo3_polars::derive::{CallerContext, DefaultKwargs};
+use pyo3_polars_derive::polars_expr;
+
+#[polars_expr(output_type=Float64)]
+fn clip_min(inputs: &[Series], threshold: f64) -> PolarsResult<Series> {
+    Ok(inputs[0]
+        .f64()?
+        .apply_values(|v| v.max(threshold))
+        .into_series())
+}
+
+#[polars_expr(output_type=String)]
+fn replace_all(
+    inputs: &[Series],
+    pattern: &str,
+    value: Option<String>,
+    context: CallerContext,
+    kwargs: DefaultKwargs,
+) -> PolarsResult<Series> {
+    let _ = (pattern, value, context, kwargs);
+    Ok(inputs[0].clone())
+}
+
+fn main() {}
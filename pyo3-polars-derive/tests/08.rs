@@ -0,0 +1,27 @@
+use polars_core::error::PolarsResult;
+use polars_core::prelude::{CompatLevel, Series};
+use pyo3_polars::derive::{polars_plugin_init, DefaultKwargs, PluginState};
+use pyo3_polars_derive::polars_expr;
+
+#[polars_plugin_init]
+fn init() -> PolarsResult<()> {
+    Ok(())
+}
+
+struct Prefix(String);
+
+impl PluginState for Prefix {
+    type Kwargs = DefaultKwargs;
+
+    fn build(kwargs: DefaultKwargs) -> PolarsResult<Self> {
+        Ok(Prefix(format!("{kwargs:?}")))
+    }
+}
+
+#[polars_expr(output_type=String)]
+fn with_prefix(inputs: &[Series], state: &Prefix) -> PolarsResult<Series> {
+    let _ = &state.0;
+    Ok(inputs[0].clone())
+}
+
+fn main() {}
@@ -0,0 +1,18 @@
+use polars_core::error::PolarsResult;
+use polars_core::prelude::{CompatLevel, Series};
+use pyo3_polars_derive::polars_expr;
+
+const BUDGET: usize = 1 << 20;
+
+#[polars_expr(output_type=Int32, memory_budget=BUDGET)]
+fn budgeted(inputs: &[Series]) -> PolarsResult<Series> {
+    pyo3_polars::check_memory_budget()?;
+    Ok(inputs[0].clone())
+}
+
+#[polars_expr(output_type=Int32, memory_budget=4 * 1024)]
+fn budgeted_literal(inputs: &[Series]) -> PolarsResult<Series> {
+    Ok(inputs[0].clone())
+}
+
+fn main() {}
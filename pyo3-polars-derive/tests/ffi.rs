@@ -0,0 +1,883 @@
+//! Calls the generated symbols the way polars does, and checks that failures are reported as the
+//! last error instead of unwinding over the FFI boundary.
+use std::ptr::NonNull;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use polars_core::export::arrow::ffi::ArrowSchema;
+use polars_core::prelude::*;
+use polars_ffi::version_0::{export_series, SeriesExport};
+use pyo3_polars::derive::testing::{
+    call_context_field, call_expr, call_field, caller_context, last_error, last_error_context,
+    last_error_kind,
+};
+use pyo3_polars::derive::{
+    plugin_stats, polars_aggregation, polars_plugin_init, serialize_kwargs, set_plugin_panic_hook,
+    Aggregation, CallerContext, DefaultKwargs, ErrorKind, KwargsEncoding, KwargsSchema,
+    PluginState, KWARGS_MAGIC,
+};
+use pyo3_polars::warnings::take_plugin_warnings;
+use pyo3_polars::{AllocatorStatus, FallbackReason, PolarsAllocator, PolarsWarning};
+use pyo3_polars_derive::polars_expr;
+use serde::{Deserialize, Serialize};
+
+#[polars_expr(output_type=Int32)]
+fn first(inputs: &[Series]) -> PolarsResult<Series> {
+    Ok(inputs[0].clone())
+}
+
+fn first_output(input_fields: &[Field], kwargs: DefaultKwargs) -> PolarsResult<Field> {
+    let _ = kwargs;
+    Ok(input_fields[0].clone())
+}
+
+#[polars_expr(output_type_func_with_kwargs=first_output)]
+fn first_with_kwargs(inputs: &[Series], kwargs: DefaultKwargs) -> PolarsResult<Series> {
+    let _ = kwargs;
+    Ok(inputs[0].clone())
+}
+
+#[derive(Serialize, Deserialize, KwargsSchema)]
+struct OffsetKwargs {
+    /// The value added to the input.
+    offset: i32,
+}
+
+fn offset_output(input_fields: &[Field], kwargs: OffsetKwargs) -> PolarsResult<Field> {
+    let _ = kwargs;
+    Ok(input_fields[0].clone())
+}
+
+#[polars_expr(output_type_func_with_kwargs=offset_output)]
+fn add_offset(inputs: &[Series], kwargs: OffsetKwargs) -> PolarsResult<Series> {
+    Ok(inputs[0]
+        .i32()?
+        .apply_values(|v| v + kwargs.offset)
+        .into_series())
+}
+
+#[polars_expr(output_type=Int32)]
+fn failing(_inputs: &[Series]) -> PolarsResult<Series> {
+    polars_bail!(ComputeError: "expected failure")
+}
+
+#[polars_expr(output_type=Int32)]
+fn missing_column(_inputs: &[Series]) -> PolarsResult<Series> {
+    Err(polars_err!(ColumnNotFound: "b")).map_err(|err| err.context("looking up the offset".into()))
+}
+
+#[polars_expr(output_type=Int32)]
+fn warning(inputs: &[Series]) -> PolarsResult<Series> {
+    pyo3_polars::warn(PolarsWarning::Performance, "computed row by row").unwrap();
+    Ok(inputs[0].clone())
+}
+
+#[polars_expr(output_type=Int32)]
+fn many_warnings(inputs: &[Series]) -> PolarsResult<Series> {
+    for i in 0..4100 {
+        pyo3_polars::warn(PolarsWarning::Performance, format!("warning {i}")).unwrap();
+    }
+    Ok(inputs[0].clone())
+}
+
+#[polars_expr]
+fn first_and_len(inputs: &[Series]) -> PolarsResult<(Int32Chunked, IdxCa)> {
+    let len = IdxCa::from_slice("".into(), &[inputs[0].len() as IdxSize]);
+    Ok((inputs[0].i32()?.head(Some(1)), len))
+}
+
+#[derive(Serialize, Deserialize)]
+struct RenameKwargs {
+    name: String,
+}
+
+fn renamed_output(
+    input_fields: &[Field],
+    _context: CallerContext,
+    kwargs: RenameKwargs,
+) -> PolarsResult<Field> {
+    Ok(Field::new(
+        kwargs.name.into(),
+        input_fields[0].dtype().clone(),
+    ))
+}
+
+#[polars_expr(output_type_func=renamed_output)]
+fn renamed(inputs: &[Series], kwargs: RenameKwargs) -> PolarsResult<Series> {
+    Ok(inputs[0].clone().with_name(kwargs.name.into()))
+}
+
+fn mode_output(_input_fields: &[Field], context: CallerContext) -> PolarsResult<Field> {
+    let name = if context.parallel() {
+        "parallel"
+    } else {
+        "sequential"
+    };
+    Ok(Field::new(name.into(), DataType::Boolean))
+}
+
+#[polars_expr(output_type_func=mode_output)]
+fn mode(inputs: &[Series], context: CallerContext) -> PolarsResult<Series> {
+    let name = if context.parallel() {
+        "parallel"
+    } else {
+        "sequential"
+    };
+    Ok(Series::new(name.into(), [context.parallel()]).new_from_index(0, inputs[0].len()))
+}
+
+fn float_output(_input_fields: &[Field]) -> PolarsResult<DataType> {
+    Ok(DataType::Float64)
+}
+
+#[polars_expr(output_type_func=float_output)]
+fn to_float(inputs: &[Series]) -> PolarsResult<Series> {
+    inputs[0].cast(&DataType::Float64)
+}
+
+#[polars_expr(output_type=Float64, dispatch=[Float32, Float64], cast_to=Float64)]
+fn double<T: PolarsNumericType>(inputs: &[&ChunkedArray<T>]) -> PolarsResult<Series> {
+    Ok((inputs[0] + inputs[0]).into_series())
+}
+
+#[polars_expr(output_type=Float64, dispatch=[Float32, Float64], cast_to=Float64)]
+fn add_pair<T: PolarsNumericType>(inputs: &[&ChunkedArray<T>]) -> PolarsResult<Series> {
+    Ok((inputs[0] + inputs[1]).into_series())
+}
+
+#[polars_expr(output_type=Float32, dispatch=[Float32], cast_to=Float32)]
+fn add_pair_f32<T: PolarsNumericType>(inputs: &[&ChunkedArray<T>]) -> PolarsResult<Series> {
+    Ok((inputs[0] + inputs[1]).into_series())
+}
+
+#[polars_expr(output_type=Int32)]
+fn add_scalar(inputs: &[Series], value: i32) -> PolarsResult<Series> {
+    Ok(inputs[0].i32()?.apply_values(|v| v + value).into_series())
+}
+
+#[polars_expr(output_type=Float64)]
+fn scale(inputs: &[Series], factor: f64) -> PolarsResult<Series> {
+    Ok((inputs[0].f64()? * factor).into_series())
+}
+
+static INIT_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+/// Only called by `expression_records_stats`, so its statistics are deterministic.
+#[polars_expr(output_type=Int32)]
+fn counted(inputs: &[Series], n: i64) -> PolarsResult<Series> {
+    polars_ensure!(!inputs[0].is_empty(), ComputeError: "empty input");
+    Ok(inputs[0].head(Some(n as usize)))
+}
+
+#[polars_plugin_init]
+fn count_init() -> PolarsResult<()> {
+    INIT_CALLS.fetch_add(1, Ordering::Relaxed);
+    Ok(())
+}
+
+static OFFSET_BUILDS: AtomicUsize = AtomicUsize::new(0);
+
+struct Offset(i32);
+
+impl PluginState for Offset {
+    type Kwargs = OffsetKwargs;
+
+    fn build(kwargs: OffsetKwargs) -> PolarsResult<Self> {
+        OFFSET_BUILDS.fetch_add(1, Ordering::Relaxed);
+        Ok(Offset(kwargs.offset))
+    }
+}
+
+#[polars_expr(output_type=Int32)]
+fn add_cached_offset(inputs: &[Series], state: &Offset) -> PolarsResult<Series> {
+    Ok(inputs[0].i32()?.apply_values(|v| v + state.0).into_series())
+}
+
+#[polars_aggregation(output_type=Int64)]
+struct Total;
+
+impl Aggregation for Total {
+    type Kwargs = ();
+    type State = i64;
+
+    fn init(_kwargs: &()) -> PolarsResult<i64> {
+        Ok(0)
+    }
+
+    fn update(state: &mut i64, inputs: &[Series]) -> PolarsResult<()> {
+        *state += inputs[0].i64()?.into_no_null_iter().sum::<i64>();
+        Ok(())
+    }
+
+    fn combine(state: &mut i64, other: i64) -> PolarsResult<()> {
+        *state += other;
+        Ok(())
+    }
+
+    fn finalize(state: i64) -> PolarsResult<Series> {
+        Ok(Series::new("total".into(), [state]))
+    }
+}
+
+#[derive(Serialize, Deserialize, KwargsSchema)]
+struct FactorKwargs {
+    /// The factor the total is multiplied with.
+    factor: i64,
+}
+
+struct Factor(i64);
+
+impl PluginState for Factor {
+    type Kwargs = FactorKwargs;
+
+    fn build(kwargs: FactorKwargs) -> PolarsResult<Self> {
+        Ok(Factor(kwargs.factor))
+    }
+}
+
+#[polars_aggregation(output_type=Int64)]
+struct ScaledTotal;
+
+impl Aggregation for ScaledTotal {
+    type Kwargs = Factor;
+    type State = (i64, i64);
+
+    fn init(kwargs: &Factor) -> PolarsResult<(i64, i64)> {
+        Ok((kwargs.0, 0))
+    }
+
+    fn update(state: &mut (i64, i64), inputs: &[Series]) -> PolarsResult<()> {
+        state.1 += inputs[0].i64()?.into_no_null_iter().sum::<i64>();
+        Ok(())
+    }
+
+    fn combine(state: &mut (i64, i64), other: (i64, i64)) -> PolarsResult<()> {
+        state.1 += other.1;
+        Ok(())
+    }
+
+    fn finalize(state: (i64, i64)) -> PolarsResult<Series> {
+        Ok(Series::new("total".into(), [state.0 * state.1]))
+    }
+}
+
+const BAD_KWARGS: &[u8] = b"not a pickle";
+
+fn int_field() -> Field {
+    Field::new("a".into(), DataType::Int32)
+}
+
+#[test]
+fn field_function_without_inputs_reports_error() {
+    let err = unsafe { call_field(_polars_plugin_field_first, &[], &[]) }.unwrap_err();
+    assert!(err.contains("expected at least one input"), "{err}");
+    assert_eq!(last_error_kind(), ErrorKind::InvalidOperation);
+}
+
+#[test]
+fn field_function_reports_bad_kwargs() {
+    let err = unsafe {
+        call_field(
+            _polars_plugin_field_first_with_kwargs,
+            &[int_field()],
+            BAD_KWARGS,
+        )
+    }
+    .unwrap_err();
+    assert!(err.contains("could not parse kwargs"), "{err}");
+}
+
+#[test]
+fn field_function_reports_released_schema() {
+    let mut fields = [ArrowSchema::empty()];
+    let mut out = ArrowSchema::empty();
+    unsafe { _polars_plugin_field_first(fields.as_mut_ptr(), 1, &mut out, std::ptr::null(), 0) };
+    assert!(out.is_null());
+    let err = last_error();
+    assert!(err.contains("could not import input field 0"), "{err}");
+}
+
+#[test]
+fn field_function_reports_null_schema() {
+    let mut out = ArrowSchema::empty();
+    unsafe { _polars_plugin_field_first(std::ptr::null_mut(), 1, &mut out, std::ptr::null(), 0) };
+    assert!(out.is_null());
+    let err = last_error();
+    assert!(err.contains("could not import the input fields"), "{err}");
+}
+
+#[test]
+fn field_function_returns_output() {
+    let kwargs = serialize_kwargs(&OffsetKwargs { offset: 1 }, KwargsEncoding::Pickle).unwrap();
+    let out = unsafe { call_field(_polars_plugin_field_add_offset, &[int_field()], &kwargs) };
+    assert_eq!(out.unwrap(), int_field());
+}
+
+#[test]
+fn field_function_overrides_name() {
+    let kwargs = serialize_kwargs(
+        &RenameKwargs {
+            name: "b".to_string(),
+        },
+        KwargsEncoding::Pickle,
+    )
+    .unwrap();
+    let out = unsafe { call_field(_polars_plugin_field_renamed, &[int_field()], &kwargs) };
+    assert_eq!(out.unwrap(), Field::new("b".into(), DataType::Int32));
+}
+
+#[test]
+fn field_function_takes_context() {
+    let out = unsafe { call_field(_polars_plugin_field_mode, &[int_field()], &[]) };
+    assert_eq!(
+        out.unwrap(),
+        Field::new("sequential".into(), DataType::Boolean)
+    );
+
+    let out = unsafe {
+        call_context_field(
+            _polars_plugin_context_field_mode,
+            &[int_field()],
+            &[],
+            caller_context(true),
+        )
+    };
+    assert_eq!(
+        out.unwrap(),
+        Field::new("parallel".into(), DataType::Boolean)
+    );
+
+    let s = Series::new("a".into(), [1i32, 2]);
+    let out = unsafe { call_expr(_polars_plugin_mode, &[s], &[], caller_context(true)) };
+    assert_eq!(out.unwrap().name().as_str(), "parallel");
+}
+
+#[test]
+fn field_function_names_dtype_after_first_input() {
+    let out = unsafe { call_field(_polars_plugin_field_to_float, &[int_field()], &[]) };
+    assert_eq!(out.unwrap(), Field::new("a".into(), DataType::Float64));
+}
+
+#[test]
+fn expression_reports_released_series_export() {
+    let mut inputs = [
+        export_series(&Series::new("a".into(), [1i32])),
+        SeriesExport::empty(),
+    ];
+    let mut out = SeriesExport::empty();
+    let mut context = CallerContext::default();
+    unsafe {
+        _polars_plugin_first(
+            inputs.as_mut_ptr(),
+            inputs.len(),
+            std::ptr::null(),
+            0,
+            &mut out,
+            &mut context,
+        )
+    };
+    // The plugin took ownership of the inputs.
+    std::mem::forget(inputs);
+    assert!(out.is_null());
+    let err = last_error();
+    assert!(err.contains("could not import input 1"), "{err}");
+}
+
+#[test]
+fn expression_reports_null_inputs() {
+    let mut out = SeriesExport::empty();
+    let mut context = CallerContext::default();
+    unsafe {
+        _polars_plugin_first(
+            std::ptr::null_mut(),
+            1,
+            std::ptr::null(),
+            0,
+            &mut out,
+            &mut context,
+        )
+    };
+    assert!(out.is_null());
+    let err = last_error();
+    assert!(err.contains("could not import the inputs"), "{err}");
+}
+
+#[test]
+fn expression_reports_bad_kwargs() {
+    let s = Series::new("a".into(), [1i32, 2, 3]);
+    let out = unsafe {
+        call_expr(
+            _polars_plugin_first_with_kwargs,
+            &[s],
+            BAD_KWARGS,
+            Default::default(),
+        )
+    };
+    let err = out.unwrap_err();
+    assert!(err.contains("could not parse kwargs"), "{err}");
+}
+
+#[test]
+fn expression_reads_kwargs_encodings() {
+    let s = Series::new("a".into(), [1i32, 2, 3]);
+    let expected = Series::new("a".into(), [11i32, 12, 13]);
+    for encoding in [
+        KwargsEncoding::Pickle,
+        KwargsEncoding::Json,
+        KwargsEncoding::Cbor,
+        KwargsEncoding::MessagePack,
+    ] {
+        let kwargs = serialize_kwargs(&OffsetKwargs { offset: 10 }, encoding).unwrap();
+        let context = caller_context(true);
+        let out = unsafe { call_expr(_polars_plugin_add_offset, &[s.clone()], &kwargs, context) };
+        assert!(out.unwrap().equals(&expected), "{encoding:?}");
+    }
+}
+
+#[test]
+fn expression_reports_unknown_kwargs_encoding() {
+    let s = Series::new("a".into(), [1i32, 2, 3]);
+    let mut kwargs = KWARGS_MAGIC.to_vec();
+    kwargs.extend([42, b'{', b'}']);
+    let out = unsafe { call_expr(_polars_plugin_add_offset, &[s], &kwargs, Default::default()) };
+    let err = out.unwrap_err();
+    assert!(err.contains("unknown kwargs encoding tag: 42"), "{err}");
+}
+
+#[test]
+fn expression_reports_missing_kwarg() {
+    let s = Series::new("a".into(), [1i32, 2, 3]);
+    #[derive(Serialize)]
+    struct NoKwargs {}
+    let kwargs = serialize_kwargs(&NoKwargs {}, KwargsEncoding::Pickle).unwrap();
+    let out = unsafe { call_expr(_polars_plugin_add_offset, &[s], &kwargs, Default::default()) };
+    let err = out.unwrap_err();
+    assert!(
+        err.contains("missing kwarg `offset` (`i32`, The value added to the input.)"),
+        "{err}"
+    );
+}
+
+#[test]
+fn field_function_reports_mistyped_kwarg() {
+    let mut kwargs = KWARGS_MAGIC.to_vec();
+    kwargs.push(KwargsEncoding::Json.tag());
+    kwargs.extend_from_slice(br#"{"offset": "ten"}"#);
+    let err = unsafe { call_field(_polars_plugin_field_add_offset, &[int_field()], &kwargs) }
+        .unwrap_err();
+    assert!(
+        err.contains("invalid kwarg `offset` (`i32`, The value added to the input.)"),
+        "{err}"
+    );
+}
+
+#[test]
+fn expression_reports_error() {
+    let s = Series::new("a".into(), [1i32, 2, 3]);
+    let out = unsafe { call_expr(_polars_plugin_failing, &[s], &[], Default::default()) };
+    let err = out.unwrap_err();
+    assert!(err.contains("expected failure"), "{err}");
+}
+
+/// Returns no output without setting an error, unlike the generated symbols.
+unsafe extern "C" fn silent_failure(
+    _: *mut SeriesExport,
+    _: usize,
+    _: *const u8,
+    _: usize,
+    _: *mut SeriesExport,
+    _: *mut CallerContext,
+) {
+}
+
+#[test]
+fn expression_does_not_report_stale_error() {
+    let s = Series::new("a".into(), [1i32]);
+    unsafe {
+        call_expr(
+            _polars_plugin_failing,
+            &[s.clone()],
+            &[],
+            Default::default(),
+        )
+    }
+    .unwrap_err();
+    let err = unsafe { call_expr(silent_failure, &[s], &[], Default::default()) }.unwrap_err();
+    assert_eq!(err, "the plugin returned no output and set no error");
+}
+
+#[test]
+fn expression_reports_error_kind_and_context() {
+    let s = Series::new("a".into(), [1i32]);
+    let out = unsafe { call_expr(_polars_plugin_missing_column, &[s], &[], Default::default()) };
+    let err = out.unwrap_err();
+    assert!(err.contains("looking up the offset"), "{err}");
+    assert_eq!(last_error_kind(), ErrorKind::ColumnNotFound);
+    assert_eq!(last_error_context(), ["looking up the offset"]);
+
+    let out = unsafe { call_expr(_polars_plugin_first, &[], &[], Default::default()) };
+    out.unwrap_err();
+    assert_eq!(last_error_kind(), ErrorKind::Panic);
+    assert!(last_error_context().is_empty());
+}
+
+#[test]
+fn expression_without_inputs_reports_panic() {
+    let out = unsafe { call_expr(_polars_plugin_first, &[], &[], Default::default()) };
+    let err = out.unwrap_err();
+    assert!(err.contains("index out of bounds"), "{err}");
+}
+
+#[test]
+fn plugin_panic_hook_is_replaceable() {
+    static PLUGIN_PANICS: AtomicUsize = AtomicUsize::new(0);
+    set_plugin_panic_hook(|_| {
+        PLUGIN_PANICS.fetch_add(1, Ordering::Relaxed);
+    });
+    let out = unsafe { call_expr(_polars_plugin_first, &[], &[], Default::default()) };
+    // Still recorded as the last error, with the location.
+    let err = out.unwrap_err();
+    assert!(err.contains("panicked at"), "{err}");
+    assert!(PLUGIN_PANICS.load(Ordering::Relaxed) >= 1);
+}
+
+#[test]
+fn expression_returns_output() {
+    let s = Series::new("a".into(), [1i32, 2, 3]);
+    let out = unsafe { call_expr(_polars_plugin_first, &[s.clone()], &[], Default::default()) };
+    assert!(out.unwrap().equals(&s));
+}
+
+#[test]
+fn dispatched_expression_casts_numeric_input() {
+    let s = Series::new("a".into(), [1i32, 2, 3]);
+    let out = unsafe { call_expr(_polars_plugin_double, &[s], &[], Default::default()) };
+    assert!(out
+        .unwrap()
+        .equals(&Series::new("a".into(), [2.0f64, 4.0, 6.0])));
+}
+
+#[test]
+fn dispatched_expression_uses_supertype_of_inputs() {
+    let a = Series::new("a".into(), [1.5f32]);
+    let b = Series::new("b".into(), [0.1f64]);
+    let out = unsafe { call_expr(_polars_plugin_add_pair, &[a, b], &[], Default::default()) };
+    let out = out.unwrap();
+    assert_eq!(out.dtype(), &DataType::Float64);
+    assert_eq!(out.f64().unwrap().get(0), Some(1.5 + 0.1));
+}
+
+#[test]
+fn dispatched_expression_rejects_narrowing_cast() {
+    let a = Series::new("a".into(), [1.5f32]);
+    let b = Series::new("b".into(), [0.1f64]);
+    let out = unsafe {
+        call_expr(
+            _polars_plugin_add_pair_f32,
+            &[a, b],
+            &[],
+            Default::default(),
+        )
+    };
+    let err = out.unwrap_err();
+    assert!(
+        err.contains("add_pair_f32 was dispatched to dtype f32, casting input b of dtype f64 to it would narrow it"),
+        "{err}"
+    );
+    assert_eq!(last_error_kind(), ErrorKind::SchemaMismatch);
+}
+
+#[test]
+fn dispatched_expression_reports_unsupported_dtype() {
+    let s = Series::new("a".into(), ["x"]);
+    let err =
+        unsafe { call_expr(_polars_plugin_double, &[s], &[], Default::default()) }.unwrap_err();
+    assert!(
+        err.contains("double doesn't support dtype str, expected one of: f32, f64"),
+        "{err}"
+    );
+}
+
+#[test]
+fn expression_reads_scalar_argument() {
+    let s = Series::new("a".into(), [1i32, 2, 3]);
+    let value = Series::new("literal".into(), [2i64]);
+    let out = unsafe {
+        call_expr(
+            _polars_plugin_add_scalar,
+            &[s, value],
+            &[],
+            Default::default(),
+        )
+    };
+    assert!(out.unwrap().equals(&Series::new("a".into(), [3i32, 4, 5])));
+}
+
+#[test]
+fn expression_reports_non_scalar_argument() {
+    let s = Series::new("a".into(), [1i32, 2, 3]);
+    let out = unsafe {
+        call_expr(
+            _polars_plugin_add_scalar,
+            &[s.clone(), s],
+            &[],
+            Default::default(),
+        )
+    };
+    let err = out.unwrap_err();
+    assert!(
+        err.contains("argument `value` must be a scalar, got a Series of length 3"),
+        "{err}"
+    );
+}
+
+#[test]
+fn expression_reports_null_scalar_argument() {
+    let s = Series::new("a".into(), [1i32, 2, 3]);
+    let value = Series::new("literal".into(), [None::<i32>]);
+    let out = unsafe {
+        call_expr(
+            _polars_plugin_add_scalar,
+            &[s, value],
+            &[],
+            Default::default(),
+        )
+    };
+    let err = out.unwrap_err();
+    assert!(err.contains("argument `value` must not be null"), "{err}");
+}
+
+#[test]
+fn expression_casts_integer_to_float_scalar_argument() {
+    let s = Series::new("a".into(), [1.0f64, 2.0]);
+    let factor = Series::new("literal".into(), [2i32]);
+    let out = unsafe { call_expr(_polars_plugin_scale, &[s, factor], &[], Default::default()) };
+    assert!(out.unwrap().equals(&Series::new("a".into(), [2.0f64, 4.0])));
+}
+
+#[test]
+fn expression_rejects_string_as_float_scalar_argument() {
+    let s = Series::new("a".into(), [1.0f64, 2.0]);
+    let factor = Series::new("literal".into(), ["1.5"]);
+    let out = unsafe { call_expr(_polars_plugin_scale, &[s, factor], &[], Default::default()) };
+    let err = out.unwrap_err();
+    assert!(
+        err.contains("invalid argument `factor` of dtype str: expected a number, got str"),
+        "{err}"
+    );
+    assert_eq!(last_error_kind(), ErrorKind::SchemaMismatch);
+}
+
+#[test]
+fn plugin_init_runs_once() {
+    let s = Series::new("a".into(), [1i32, 2, 3]);
+    for _ in 0..2 {
+        let out = unsafe { call_expr(_polars_plugin_first, &[s.clone()], &[], Default::default()) };
+        assert!(out.is_ok());
+    }
+    assert_eq!(INIT_CALLS.load(Ordering::Relaxed), 1);
+}
+
+#[test]
+fn plugin_state_is_cached_by_kwargs() {
+    let kwargs = serialize_kwargs(&OffsetKwargs { offset: 40 }, KwargsEncoding::Pickle).unwrap();
+    let s = Series::new("a".into(), [1i32, 2]);
+    for _ in 0..3 {
+        let out = unsafe {
+            call_expr(
+                _polars_plugin_add_cached_offset,
+                &[s.clone()],
+                &kwargs,
+                Default::default(),
+            )
+        };
+        assert!(out.unwrap().equals(&Series::new("a".into(), [41i32, 42])));
+    }
+    assert_eq!(OFFSET_BUILDS.load(Ordering::Relaxed), 1);
+}
+
+#[test]
+fn struct_expression_returns_struct() {
+    let s = Series::new("a".into(), [1i32, 2, 3]);
+    let out = unsafe { call_expr(_polars_plugin_first_and_len, &[s], &[], Default::default()) };
+    let out = out.unwrap();
+    assert_eq!(out.name().as_str(), "a");
+
+    let fields = out.struct_().unwrap().fields_as_series();
+    assert_eq!(fields[0].name().as_str(), "field_0");
+    assert!(fields[0].equals(&Series::new("field_0".into(), [1i32])));
+    assert_eq!(fields[1].idx().unwrap().get(0), Some(3));
+}
+
+#[test]
+fn aggregation_returns_output() {
+    let s = Series::new("a".into(), [1i64, 2, 3]);
+    let out = unsafe { call_expr(_polars_plugin_total, &[s], &[], Default::default()) };
+    assert!(out.unwrap().equals(&Series::new("total".into(), [6i64])));
+}
+
+#[test]
+fn aggregation_combines_partial_states() {
+    unsafe {
+        let a = _polars_plugin_agg_init_total(NonNull::dangling().as_ptr(), 0);
+        let b = _polars_plugin_agg_init_total(NonNull::dangling().as_ptr(), 0);
+        for (state, values) in [(a, [1i64, 2]), (b, [3, 4])] {
+            let mut inputs = [export_series(&Series::new("a".into(), values))];
+            assert!(_polars_plugin_agg_update_total(
+                state,
+                inputs.as_mut_ptr(),
+                1
+            ));
+            std::mem::forget(inputs);
+        }
+        assert!(_polars_plugin_agg_combine_total(a, b));
+
+        let mut return_value = SeriesExport::empty();
+        assert!(_polars_plugin_agg_finalize_total(a, &mut return_value));
+        let out = polars_ffi::version_0::import_series(return_value).unwrap();
+        assert!(out.equals(&Series::new("total".into(), [10i64])));
+    }
+}
+
+#[test]
+fn aggregation_update_reports_error() {
+    unsafe {
+        let state = _polars_plugin_agg_init_total(NonNull::dangling().as_ptr(), 0);
+        let mut inputs = [export_series(&Series::new("a".into(), ["x"]))];
+        assert!(!_polars_plugin_agg_update_total(
+            state,
+            inputs.as_mut_ptr(),
+            1
+        ));
+        std::mem::forget(inputs);
+        _polars_plugin_agg_drop_total(state);
+    }
+    assert!(
+        last_error().contains("invalid series dtype"),
+        "{}",
+        last_error()
+    );
+}
+
+#[test]
+fn aggregation_builds_state_from_kwargs() {
+    let s = Series::new("a".into(), [1i64, 2, 3]);
+    let kwargs = serialize_kwargs(&FactorKwargs { factor: 2 }, KwargsEncoding::Pickle).unwrap();
+    let out = unsafe {
+        call_expr(
+            _polars_plugin_scaled_total,
+            &[s],
+            &kwargs,
+            Default::default(),
+        )
+    };
+    assert!(out.unwrap().equals(&Series::new("total".into(), [12i64])));
+}
+
+#[test]
+fn aggregation_reports_missing_kwarg() {
+    #[derive(Serialize)]
+    struct NoKwargs {}
+    let kwargs = serialize_kwargs(&NoKwargs {}, KwargsEncoding::Pickle).unwrap();
+    let state = unsafe { _polars_plugin_agg_init_scaled_total(kwargs.as_ptr(), kwargs.len()) };
+    assert!(state.is_null());
+    let err = last_error();
+    assert!(
+        err.contains("missing kwarg `factor` (`i64`, The factor the total is multiplied with.)"),
+        "{err}"
+    );
+}
+
+#[test]
+fn aggregation_reports_null_state() {
+    unsafe {
+        let state = _polars_plugin_agg_init_total(NonNull::dangling().as_ptr(), 0);
+        assert!(!_polars_plugin_agg_combine_total(
+            state,
+            std::ptr::null_mut()
+        ));
+        assert_eq!(last_error_kind(), ErrorKind::InvalidOperation);
+
+        let mut return_value = SeriesExport::empty();
+        assert!(!_polars_plugin_agg_finalize_total(
+            std::ptr::null_mut(),
+            &mut return_value
+        ));
+        assert!(last_error().contains("the aggregation state is null"));
+
+        _polars_plugin_agg_drop_total(state);
+        _polars_plugin_agg_drop_total(std::ptr::null_mut());
+        assert!(last_error().contains("the aggregation state is null"));
+    }
+}
+
+#[test]
+fn expression_records_stats() {
+    let s = Series::new("a".into(), [1i32, 2, 3]);
+    let empty = Series::new_empty("a".into(), &DataType::Int32);
+    let n = Series::new("literal".into(), [1i64]);
+    unsafe {
+        call_expr(
+            _polars_plugin_counted,
+            &[s, n.clone()],
+            &[],
+            Default::default(),
+        )
+        .unwrap();
+        call_expr(_polars_plugin_counted, &[empty, n], &[], Default::default()).unwrap_err();
+        call_expr(_polars_plugin_counted, &[], &[], Default::default()).unwrap_err();
+    }
+
+    let stats = plugin_stats()
+        .into_iter()
+        .find(|stats| stats.name == "counted")
+        .unwrap();
+    // The scalar argument isn't an input row. The call without inputs returns before the function
+    // is called, which also counts as an error.
+    assert_eq!(stats.calls, 3);
+    assert_eq!(stats.errors, 2);
+    assert_eq!(stats.rows_in, 3);
+    assert_eq!(stats.rows_out, 1);
+}
+
+#[test]
+fn allocator_reports_fallback() {
+    let alloc = PolarsAllocator::new();
+    assert_eq!(
+        alloc.status(),
+        AllocatorStatus::Fallback(FallbackReason::PythonNotInitialized)
+    );
+}
+
+/// The warnings queue is global, so tests taking from it don't run at the same time.
+static WARNING_TESTS: Mutex<()> = Mutex::new(());
+
+#[test]
+fn expression_buffers_warnings() {
+    let _guard = WARNING_TESTS.lock().unwrap_or_else(|err| err.into_inner());
+    let s = Series::new("a".into(), [1i32]);
+    unsafe { call_expr(_polars_plugin_warning, &[s], &[], Default::default()) }.unwrap();
+
+    let warnings = take_plugin_warnings();
+    let warning = warnings
+        .iter()
+        .find(|warning| warning.message == "computed row by row")
+        .unwrap();
+    assert_eq!(warning.kind, PolarsWarning::Performance);
+}
+
+#[test]
+fn plugin_warnings_drop_the_oldest_when_full() {
+    let _guard = WARNING_TESTS.lock().unwrap_or_else(|err| err.into_inner());
+    take_plugin_warnings();
+    let s = Series::new("a".into(), [1i32]);
+    unsafe { call_expr(_polars_plugin_many_warnings, &[s], &[], Default::default()) }.unwrap();
+
+    let warnings = take_plugin_warnings();
+    assert_eq!(warnings.len(), 4097);
+    assert_eq!(warnings[0].kind, PolarsWarning::Polars);
+    assert_eq!(warnings[0].message, "dropped 4 warnings of the plugin");
+    assert_eq!(warnings[1].message, "warning 4");
+    assert_eq!(warnings[4096].message, "warning 4099");
+    assert!(take_plugin_warnings().is_empty());
+}
@@ -0,0 +1,145 @@
+//! Generates the Python registration module and `.pyi` stubs of a pyo3-polars plugin from its
+//! `#[polars_expr]` definitions.
+//!
+//! The sources are parsed with `syn`; nothing has to be compiled or loaded. See the crate
+//! README for the supported attributes.
+//!
+//! ```no_run
+//! pyo3_polars_stubgen::Generator::new()
+//!     .source("src")
+//!     .write("expression_lib/_plugins.py")
+//!     .unwrap();
+//! ```
+#![deny(missing_docs)]
+mod parse;
+mod render;
+
+use std::path::{Path, PathBuf};
+
+pub use parse::{KwargsField, PluginFunction, PluginKwargs, ScalarParam};
+use thiserror::Error;
+
+/// Errors that can occur while generating the Python module.
+#[derive(Error, Debug)]
+pub enum StubgenError {
+    /// A source file or output file could not be read or written.
+    #[error("{path}: {error}")]
+    Io {
+        /// The file that failed.
+        path: PathBuf,
+        /// The underlying error.
+        error: std::io::Error,
+    },
+    /// A source file could not be parsed.
+    #[error("{path}: {error}")]
+    Parse {
+        /// The file that failed.
+        path: PathBuf,
+        /// The underlying error.
+        error: syn::Error,
+    },
+    /// A `polars_expr` definition can't be mapped to Python.
+    #[error("{0}")]
+    Unsupported(String),
+}
+
+/// Result type of this crate.
+pub type StubgenResult<T> = Result<T, StubgenError>;
+
+/// The generated Python module and its type stubs.
+#[derive(Debug, Clone)]
+pub struct GeneratedModule {
+    /// Contents of the `.py` module.
+    pub module: String,
+    /// Contents of the `.pyi` stubs.
+    pub stubs: String,
+}
+
+/// Collects `#[polars_expr]` functions from Rust sources and renders the Python side.
+#[derive(Debug, Clone, Default)]
+pub struct Generator {
+    sources: Vec<PathBuf>,
+}
+
+impl Generator {
+    /// Create a `Generator` without sources.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a Rust source file, or all `.rs` files below a directory.
+    pub fn source(mut self, path: impl Into<PathBuf>) -> Self {
+        self.sources.push(path.into());
+        self
+    }
+
+    /// Parse the sources and return the plugin functions in source order.
+    pub fn functions(&self) -> StubgenResult<Vec<PluginFunction>> {
+        let mut files = Vec::new();
+        for path in &self.sources {
+            collect_rust_files(path, &mut files)?;
+        }
+
+        let mut parsed = Vec::with_capacity(files.len());
+        for path in files {
+            let source = std::fs::read_to_string(&path).map_err(|error| StubgenError::Io {
+                path: path.clone(),
+                error,
+            })?;
+            let file =
+                syn::parse_file(&source).map_err(|error| StubgenError::Parse { path, error })?;
+            parsed.push(file);
+        }
+        parse::plugin_functions(&parsed)
+    }
+
+    /// Render the Python module and stubs.
+    pub fn generate(&self) -> StubgenResult<GeneratedModule> {
+        let functions = self.functions()?;
+        Ok(GeneratedModule {
+            module: render::module(&functions),
+            stubs: render::stubs(&functions),
+        })
+    }
+
+    /// Write the Python module to `path` and the stubs next to it with a `.pyi` extension.
+    pub fn write(&self, path: impl AsRef<Path>) -> StubgenResult<()> {
+        let path = path.as_ref();
+        let generated = self.generate()?;
+        write_if_changed(path, &generated.module)?;
+        write_if_changed(&path.with_extension("pyi"), &generated.stubs)
+    }
+}
+
+fn collect_rust_files(path: &Path, out: &mut Vec<PathBuf>) -> StubgenResult<()> {
+    let io_err = |error| StubgenError::Io {
+        path: path.to_path_buf(),
+        error,
+    };
+    if path.is_dir() {
+        let mut entries = std::fs::read_dir(path)
+            .map_err(io_err)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(io_err)?;
+        // Keep the output stable regardless of the directory order of the platform.
+        entries.sort();
+        for entry in entries {
+            collect_rust_files(&entry, out)?;
+        }
+    } else if path.extension().is_some_and(|ext| ext == "rs") {
+        out.push(path.to_path_buf());
+    }
+    Ok(())
+}
+
+// Don't touch the file if nothing changed, so tools watching the Python package don't reload.
+fn write_if_changed(path: &Path, contents: &str) -> StubgenResult<()> {
+    if std::fs::read_to_string(path).is_ok_and(|current| current == contents) {
+        return Ok(());
+    }
+    std::fs::write(path, contents).map_err(|error| StubgenError::Io {
+        path: path.to_path_buf(),
+        error,
+    })
+}
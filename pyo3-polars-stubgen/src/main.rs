@@ -0,0 +1,29 @@
+//! `pyo3-polars-stubgen <SOURCES> <OUTPUT.py>`
+//!
+//! Writes the Python registration module to `OUTPUT.py` and its stubs to `OUTPUT.pyi`.
+use std::process::ExitCode;
+
+use pyo3_polars_stubgen::Generator;
+
+fn main() -> ExitCode {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let [sources @ .., output] = args.as_slice() else {
+        eprintln!("usage: pyo3-polars-stubgen <SOURCES>... <OUTPUT.py>");
+        return ExitCode::FAILURE;
+    };
+    if sources.is_empty() {
+        eprintln!("usage: pyo3-polars-stubgen <SOURCES>... <OUTPUT.py>");
+        return ExitCode::FAILURE;
+    }
+
+    let generator = sources.iter().fold(Generator::new(), |generator, source| {
+        generator.source(source)
+    });
+    match generator.write(output) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,500 @@
+use std::collections::HashMap;
+
+use syn::ext::IdentExt;
+use syn::punctuated::Punctuated;
+use syn::{Expr, FnArg, Item, Lit, Meta, Token};
+
+use crate::{StubgenError, StubgenResult};
+
+/// A `#[polars_expr]` function or `#[polars_aggregation]` as seen from Python.
+#[derive(Debug, Clone)]
+pub struct PluginFunction {
+    /// The registered `function_name`: the name of the Rust function, or of the aggregation.
+    pub name: String,
+    /// Doc comment lines of the Rust function.
+    pub doc: Vec<String>,
+    /// Names of the expression arguments, if given with `args = [..]`.
+    pub args: Option<Vec<String>>,
+    /// The scalar arguments of the Rust function, passed as literals after the expressions.
+    pub scalars: Vec<ScalarParam>,
+    /// Flags forwarded to `register_plugin_function`, in attribute order.
+    pub flags: Vec<(String, bool)>,
+    /// Expression namespace the function is exposed on.
+    pub namespace: Option<String>,
+    /// The kwargs the function accepts.
+    pub kwargs: Option<PluginKwargs>,
+}
+
+/// A scalar argument of a plugin function, e.g. `threshold: f64`.
+#[derive(Debug, Clone)]
+pub struct ScalarParam {
+    /// Python parameter name, the name of the Rust argument.
+    pub name: String,
+    /// Python type annotation.
+    pub python_type: String,
+}
+
+/// The kwargs of a plugin function.
+#[derive(Debug, Clone)]
+pub enum PluginKwargs {
+    /// A kwargs struct that was found in the sources.
+    Struct(Vec<KwargsField>),
+    /// A kwargs type without a known schema, e.g. `DefaultKwargs`.
+    Opaque,
+}
+
+/// A single field of a kwargs struct.
+#[derive(Debug, Clone)]
+pub struct KwargsField {
+    /// Python keyword, after applying `#[serde(rename)]`.
+    pub name: String,
+    /// Python type annotation.
+    pub python_type: String,
+    /// Doc comment lines of the field.
+    pub doc: Vec<String>,
+    /// Whether the field may be omitted, because of `#[serde(default)]` or an `Option` type.
+    pub optional: bool,
+}
+
+const FLAGS: [&str; 4] = [
+    "is_elementwise",
+    "returns_scalar",
+    "changes_length",
+    "cast_to_supertype",
+];
+
+/// Names the generated Python function uses itself, so parameters can't take them.
+const RESERVED_NAMES: [&str; 7] = [
+    "expr",
+    "args",
+    "kwargs",
+    "self",
+    "pl",
+    "LIB",
+    "register_plugin_function",
+];
+
+const PYTHON_KEYWORDS: [&str; 35] = [
+    "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class", "continue",
+    "def", "del", "elif", "else", "except", "finally", "for", "from", "global", "if", "import",
+    "in", "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return", "try", "while",
+    "with", "yield",
+];
+
+/// An item carrying one of the plugin attributes.
+enum PluginItem<'a> {
+    Expr(&'a syn::ItemFn, &'a syn::Attribute),
+    Aggregation(&'a syn::ItemStruct, &'a syn::Attribute),
+}
+
+#[derive(Default)]
+struct Items<'a> {
+    plugins: Vec<PluginItem<'a>>,
+    structs: HashMap<String, &'a syn::ItemStruct>,
+    /// The `Kwargs` of the `Aggregation` impls, by type name.
+    aggregation_kwargs: HashMap<String, &'a syn::Type>,
+    /// The `Kwargs` of the `PluginState` impls, by type name.
+    state_kwargs: HashMap<String, &'a syn::Type>,
+}
+
+pub(crate) fn plugin_functions(files: &[syn::File]) -> StubgenResult<Vec<PluginFunction>> {
+    let mut items = Items::default();
+    for file in files {
+        visit_items(&file.items, &mut items);
+    }
+
+    items
+        .plugins
+        .iter()
+        .map(|plugin| match plugin {
+            PluginItem::Expr(func, attr) => plugin_function(func, attr, &items),
+            PluginItem::Aggregation(item, attr) => aggregation(item, attr, &items),
+        })
+        .map(|function| function.and_then(check_names))
+        .collect()
+}
+
+fn visit_items<'a>(items: &'a [Item], out: &mut Items<'a>) {
+    for item in items {
+        match item {
+            Item::Fn(func) => {
+                if let Some(attr) = find_attr(&func.attrs, "polars_expr") {
+                    out.plugins.push(PluginItem::Expr(func, attr))
+                }
+            }
+            Item::Struct(item) => {
+                if let Some(attr) = find_attr(&item.attrs, "polars_aggregation") {
+                    out.plugins.push(PluginItem::Aggregation(item, attr))
+                }
+                out.structs.insert(item.ident.to_string(), item);
+            }
+            Item::Impl(item) => {
+                let trait_name = item
+                    .trait_
+                    .as_ref()
+                    .and_then(|(_, path, _)| path.segments.last())
+                    .map(|segment| segment.ident.to_string());
+                let kwargs = item.items.iter().find_map(|item| match item {
+                    syn::ImplItem::Type(ty) if ty.ident == "Kwargs" => Some(&ty.ty),
+                    _ => None,
+                });
+                let by_type = match trait_name.as_deref() {
+                    Some("Aggregation") => &mut out.aggregation_kwargs,
+                    Some("PluginState") => &mut out.state_kwargs,
+                    _ => continue,
+                };
+                if let (Some(segment), Some(kwargs)) = (last_segment(&item.self_ty), kwargs) {
+                    by_type.insert(segment.ident.to_string(), kwargs);
+                }
+            }
+            Item::Mod(item) => {
+                if let Some((_, items)) = &item.content {
+                    visit_items(items, out)
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn find_attr<'a>(attrs: &'a [syn::Attribute], name: &str) -> Option<&'a syn::Attribute> {
+    attrs.iter().find(|attr| {
+        attr.path()
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == name)
+    })
+}
+
+/// Reads the parameters after the inputs the way `#[polars_expr]` does: scalar arguments, then
+/// optionally `context`, then `kwargs` or `state: &T`, whose kwargs are the `Kwargs` of its
+/// `PluginState` impl.
+fn plugin_function(
+    func: &syn::ItemFn,
+    attr: &syn::Attribute,
+    items: &Items,
+) -> StubgenResult<PluginFunction> {
+    let name = func.sig.ident.to_string();
+    let unsupported = |msg: &str| StubgenError::Unsupported(format!("`{name}`: {msg}"));
+
+    let mut function = PluginFunction {
+        name: name.clone(),
+        doc: doc_lines(&func.attrs),
+        args: None,
+        scalars: Vec::new(),
+        flags: Vec::new(),
+        namespace: None,
+        kwargs: None,
+    };
+    apply_options(&mut function, attr)?;
+
+    let mut params = Vec::new();
+    for input in func.sig.inputs.iter().skip(1) {
+        let FnArg::Typed(pat) = input else {
+            return Err(unsupported("expected a typed argument"));
+        };
+        let syn::Pat::Ident(ident) = pat.pat.as_ref() else {
+            return Err(unsupported("expected an argument"));
+        };
+        params.push((ident.ident.unraw().to_string(), pat.ty.as_ref()));
+    }
+
+    match params.last() {
+        Some((param, ty)) if param == "kwargs" => {
+            function.kwargs = Some(kwargs(ty, &items.structs));
+            params.pop();
+        }
+        Some((param, ty)) if param == "state" => {
+            let kwargs_ty = last_segment(ty)
+                .and_then(|segment| items.state_kwargs.get(&segment.ident.to_string()));
+            function.kwargs = Some(match kwargs_ty {
+                Some(kwargs_ty) => kwargs(kwargs_ty, &items.structs),
+                None => PluginKwargs::Opaque,
+            });
+            params.pop();
+        }
+        _ => {}
+    }
+    if matches!(params.last(), Some((param, _)) if param == "context") {
+        params.pop();
+    }
+    function.scalars = params
+        .into_iter()
+        .map(|(name, ty)| ScalarParam {
+            name,
+            python_type: python_type(ty),
+        })
+        .collect();
+
+    Ok(function)
+}
+
+/// Rejects parameter names that would generate invalid Python or shadow the names the generated
+/// function uses.
+fn check_names(function: PluginFunction) -> StubgenResult<PluginFunction> {
+    let mut names = function
+        .args
+        .clone()
+        .unwrap_or_else(|| vec!["expr".to_string()]);
+    names.extend(function.scalars.iter().map(|scalar| scalar.name.clone()));
+    if let Some(PluginKwargs::Struct(fields)) = &function.kwargs {
+        names.extend(fields.iter().map(|field| field.name.clone()));
+    }
+
+    // The first expression is `expr` by default.
+    let exprs = function.args.as_ref().map_or(1, Vec::len);
+    for (i, name) in names.iter().enumerate() {
+        let unsupported =
+            |msg: &str| StubgenError::Unsupported(format!("`{}`: {msg}", function.name));
+        if !is_python_identifier(name) {
+            return Err(unsupported(&format!("`{name}` is not a Python identifier")));
+        }
+        if RESERVED_NAMES.contains(&name.as_str()) && !(i < exprs && name == "expr") {
+            return Err(unsupported(&format!(
+                "the parameter `{name}` collides with a name the generated Python function uses"
+            )));
+        }
+        if names[..i].contains(name) {
+            return Err(unsupported(&format!(
+                "the parameter `{name}` collides with another parameter"
+            )));
+        }
+    }
+    Ok(function)
+}
+
+fn is_python_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|first| first == '_' || first.is_alphabetic())
+        && chars.all(|c| c == '_' || c.is_alphanumeric())
+        && !PYTHON_KEYWORDS.contains(&name)
+}
+
+fn aggregation(
+    item: &syn::ItemStruct,
+    attr: &syn::Attribute,
+    items: &Items,
+) -> StubgenResult<PluginFunction> {
+    let mut function = PluginFunction {
+        name: snake_case(&item.ident.to_string()),
+        doc: doc_lines(&item.attrs),
+        args: None,
+        scalars: Vec::new(),
+        flags: Vec::new(),
+        namespace: None,
+        kwargs: None,
+    };
+    apply_options(&mut function, attr)?;
+
+    // An aggregation produces a single value per group.
+    if !function
+        .flags
+        .iter()
+        .any(|(flag, _)| flag == "returns_scalar")
+    {
+        function.flags.push(("returns_scalar".to_string(), true));
+    }
+    function.kwargs = items
+        .aggregation_kwargs
+        .get(&item.ident.to_string())
+        .filter(|ty| !matches!(ty, syn::Type::Tuple(tuple) if tuple.elems.is_empty()))
+        .map(|ty| kwargs(ty, &items.structs));
+    Ok(function)
+}
+
+/// `GeometricMean` -> `geometric_mean`, as done by `#[polars_aggregation]`.
+fn snake_case(name: &str) -> String {
+    let mut out = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() {
+            if i > 0 {
+                out.push('_');
+            }
+            out.extend(c.to_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Apply the Python-facing options of the attribute.
+fn apply_options(function: &mut PluginFunction, attr: &syn::Attribute) -> StubgenResult<()> {
+    let name = function.name.clone();
+    let unsupported = |msg: &str| StubgenError::Unsupported(format!("`{name}`: {msg}"));
+
+    let metas = attr
+        .parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)
+        .map_err(|e| unsupported(&e.to_string()))?;
+    for meta in metas {
+        let Meta::NameValue(meta) = meta else {
+            continue;
+        };
+        let Some(key) = meta.path.get_ident().map(|ident| ident.to_string()) else {
+            continue;
+        };
+        match (key.as_str(), &meta.value) {
+            (flag, Expr::Lit(lit)) if FLAGS.contains(&flag) => match &lit.lit {
+                Lit::Bool(value) => function.flags.push((flag.to_string(), value.value)),
+                _ => return Err(unsupported(&format!("expected a boolean for `{key}`"))),
+            },
+            ("args", Expr::Array(array)) => {
+                let args = array
+                    .elems
+                    .iter()
+                    .map(|elem| match elem {
+                        Expr::Path(path) => path
+                            .path
+                            .get_ident()
+                            .map(|ident| ident.to_string())
+                            .ok_or_else(|| unsupported("expected identifiers in `args`")),
+                        _ => Err(unsupported("expected identifiers in `args`")),
+                    })
+                    .collect::<StubgenResult<Vec<_>>>()?;
+                if args.is_empty() {
+                    return Err(unsupported("`args` should name at least one argument"));
+                }
+                function.args = Some(args)
+            }
+            ("namespace", Expr::Path(path)) => {
+                function.namespace = path.path.get_ident().map(|ident| ident.to_string())
+            }
+            ("name", Expr::Path(path)) => {
+                if let Some(ident) = path.path.get_ident() {
+                    function.name = ident.to_string()
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn kwargs(ty: &syn::Type, structs: &HashMap<String, &syn::ItemStruct>) -> PluginKwargs {
+    let item = last_segment(ty).and_then(|segment| structs.get(&segment.ident.to_string()));
+    let Some(item) = item else {
+        return PluginKwargs::Opaque;
+    };
+    let syn::Fields::Named(fields) = &item.fields else {
+        return PluginKwargs::Opaque;
+    };
+
+    let container = serde_attrs(&item.attrs);
+    let fields = fields
+        .named
+        .iter()
+        .map(|field| {
+            let serde = serde_attrs(&field.attrs);
+            let name = serde
+                .rename
+                .unwrap_or_else(|| field.ident.as_ref().unwrap().unraw().to_string());
+            let is_option = last_segment(&field.ty).is_some_and(|s| s.ident == "Option");
+            KwargsField {
+                name,
+                python_type: python_type(&field.ty),
+                doc: doc_lines(&field.attrs),
+                optional: container.default || serde.default || is_option,
+            }
+        })
+        .collect();
+    PluginKwargs::Struct(fields)
+}
+
+#[derive(Default)]
+struct SerdeAttrs {
+    default: bool,
+    rename: Option<String>,
+}
+
+fn serde_attrs(attrs: &[syn::Attribute]) -> SerdeAttrs {
+    let mut out = SerdeAttrs::default();
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("serde")) {
+        // Attributes we don't understand are left to serde to complain about.
+        let _ = attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("default") {
+                out.default = true;
+                if meta.input.peek(Token![=]) {
+                    meta.value()?.parse::<syn::LitStr>()?;
+                }
+            } else if meta.path.is_ident("rename") && meta.input.peek(Token![=]) {
+                out.rename = Some(meta.value()?.parse::<syn::LitStr>()?.value());
+            } else if meta.input.peek(Token![=]) {
+                meta.value()?.parse::<Expr>()?;
+            } else if !meta.input.is_empty() && !meta.input.peek(Token![,]) {
+                meta.input.parse::<proc_macro2::TokenTree>()?;
+            }
+            Ok(())
+        });
+    }
+    out
+}
+
+fn doc_lines(attrs: &[syn::Attribute]) -> Vec<String> {
+    attrs
+        .iter()
+        .filter_map(|attr| match &attr.meta {
+            Meta::NameValue(meta) if meta.path.is_ident("doc") => match &meta.value {
+                Expr::Lit(syn::ExprLit {
+                    lit: Lit::Str(doc), ..
+                }) => {
+                    let doc = doc.value();
+                    Some(doc.strip_prefix(' ').unwrap_or(&doc).trim_end().to_string())
+                }
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+fn last_segment(ty: &syn::Type) -> Option<&syn::PathSegment> {
+    match ty {
+        syn::Type::Path(path) => path.path.segments.last(),
+        syn::Type::Reference(reference) => last_segment(&reference.elem),
+        _ => None,
+    }
+}
+
+fn generic_args(segment: &syn::PathSegment) -> Vec<&syn::Type> {
+    match &segment.arguments {
+        syn::PathArguments::AngleBracketed(args) => args
+            .args
+            .iter()
+            .filter_map(|arg| match arg {
+                syn::GenericArgument::Type(ty) => Some(ty),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Map a Rust type to the Python type that pickles into it.
+pub(crate) fn python_type(ty: &syn::Type) -> String {
+    if let syn::Type::Tuple(tuple) = ty {
+        let elems = tuple.elems.iter().map(python_type).collect::<Vec<_>>();
+        return format!("tuple[{}]", elems.join(", "));
+    }
+    let Some(segment) = last_segment(ty) else {
+        return "Any".to_string();
+    };
+    let args = generic_args(segment);
+    match (segment.ident.to_string().as_str(), args.as_slice()) {
+        ("bool", _) => "bool".to_string(),
+        ("f32" | "f64", _) => "float".to_string(),
+        (
+            "i8" | "i16" | "i32" | "i64" | "i128" | "isize" | "u8" | "u16" | "u32" | "u64" | "u128"
+            | "usize",
+            _,
+        ) => "int".to_string(),
+        ("String" | "str" | "PlSmallStr", _) => "str".to_string(),
+        ("Option", [inner]) => format!("{} | None", python_type(inner)),
+        ("Vec", [inner]) => format!("list[{}]", python_type(inner)),
+        ("HashMap" | "BTreeMap" | "PlHashMap", [key, value, ..]) => {
+            format!("dict[{}, {}]", python_type(key), python_type(value))
+        }
+        _ => "Any".to_string(),
+    }
+}
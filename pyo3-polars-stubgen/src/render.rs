@@ -0,0 +1,304 @@
+use std::fmt::Write;
+
+use crate::parse::{KwargsField, PluginFunction, PluginKwargs, ScalarParam};
+
+const HEADER: &str = "\
+# This file is generated by pyo3-polars-stubgen from the `#[polars_expr]` definitions.
+# Do not edit it by hand.
+from __future__ import annotations
+";
+
+/// The Python parameters of a function, split in expression arguments and kwargs.
+struct Signature<'a> {
+    args: Vec<String>,
+    /// `*args` catches the remaining expressions if no `args` attribute was given.
+    variadic: bool,
+    /// Passed as literals after the expressions, keyword-only after `*args`.
+    scalars: &'a [ScalarParam],
+    kwargs: &'a Option<PluginKwargs>,
+}
+
+impl<'a> Signature<'a> {
+    fn new(function: &'a PluginFunction) -> Self {
+        match &function.args {
+            Some(args) => Signature {
+                args: args.clone(),
+                variadic: false,
+                scalars: &function.scalars,
+                kwargs: &function.kwargs,
+            },
+            None => Signature {
+                args: vec!["expr".to_string()],
+                variadic: true,
+                scalars: &function.scalars,
+                kwargs: &function.kwargs,
+            },
+        }
+    }
+
+    /// Render the parameter list. `skip` drops leading expression arguments, which are bound
+    /// to `self._expr` in namespace methods.
+    fn params(&self, skip: usize) -> Vec<String> {
+        let mut params = self
+            .args
+            .iter()
+            .skip(skip)
+            .map(|arg| format!("{arg}: IntoExprColumn"))
+            .collect::<Vec<_>>();
+        if self.variadic {
+            params.push("*args: IntoExprColumn".to_string());
+        }
+        params.extend(
+            self.scalars
+                .iter()
+                .map(|scalar| format!("{}: {}", scalar.name, scalar.python_type)),
+        );
+        match self.kwargs {
+            Some(PluginKwargs::Struct(fields)) if !fields.is_empty() => {
+                if !self.variadic {
+                    params.push("*".to_string());
+                }
+                params.extend(fields.iter().map(|field| {
+                    if field.optional {
+                        format!("{}: {} | None = None", field.name, field.python_type)
+                    } else {
+                        format!("{}: {}", field.name, field.python_type)
+                    }
+                }));
+            }
+            Some(PluginKwargs::Opaque) => params.push("**kwargs: Any".to_string()),
+            _ => {}
+        }
+        params
+    }
+
+    /// The arguments to forward the parameters to the module level function.
+    fn forward(&self, skip: usize) -> Vec<String> {
+        let mut forward = self.args.iter().skip(skip).cloned().collect::<Vec<_>>();
+        if self.variadic {
+            forward.push("*args".to_string());
+        }
+        forward.extend(
+            self.scalars
+                .iter()
+                .map(|s| format!("{}={}", s.name, s.name)),
+        );
+        match self.kwargs {
+            Some(PluginKwargs::Struct(fields)) => {
+                forward.extend(fields.iter().map(|f| format!("{}={}", f.name, f.name)))
+            }
+            Some(PluginKwargs::Opaque) => forward.push("**kwargs".to_string()),
+            None => {}
+        }
+        forward
+    }
+}
+
+pub(crate) fn module(functions: &[PluginFunction]) -> String {
+    let mut out = String::from(HEADER);
+    out.push_str(
+        "
+from pathlib import Path
+from typing import TYPE_CHECKING, Any, Union
+
+import polars as pl
+from polars.plugins import register_plugin_function
+
+if TYPE_CHECKING:
+    import sys
+
+    if sys.version_info >= (3, 10):
+        from typing import TypeAlias
+    else:
+        from typing_extensions import TypeAlias
+
+    IntoExprColumn: TypeAlias = Union[pl.Expr, str, pl.Series]
+
+LIB = Path(__file__).parent
+",
+    );
+
+    for function in functions {
+        let signature = Signature::new(function);
+        writeln!(
+            out,
+            "\n\ndef {}({}) -> pl.Expr:",
+            function.name,
+            signature.params(0).join(", ")
+        )
+        .unwrap();
+        write_docstring(&mut out, "    ", &function.doc, function.kwargs.as_ref());
+        write_body(&mut out, function, &signature);
+    }
+    write_namespaces(&mut out, functions, false);
+    out
+}
+
+pub(crate) fn stubs(functions: &[PluginFunction]) -> String {
+    let mut out = String::from(HEADER);
+    out.push_str(
+        "
+from typing import Any, Union
+
+import polars as pl
+from typing_extensions import TypeAlias
+
+IntoExprColumn: TypeAlias = Union[pl.Expr, str, pl.Series]
+",
+    );
+
+    for function in functions {
+        let signature = Signature::new(function);
+        writeln!(
+            out,
+            "\ndef {}({}) -> pl.Expr: ...",
+            function.name,
+            signature.params(0).join(", ")
+        )
+        .unwrap();
+    }
+    write_namespaces(&mut out, functions, true);
+    out
+}
+
+fn write_body(out: &mut String, function: &PluginFunction, signature: &Signature) {
+    let mut args = signature.args.join(", ");
+    if signature.variadic {
+        args.push_str(", *args");
+    }
+    // The Rust function reads its scalar arguments from the last inputs.
+    for scalar in signature.scalars {
+        write!(args, ", pl.lit({})", scalar.name).unwrap();
+    }
+
+    match &function.kwargs {
+        Some(PluginKwargs::Struct(fields)) => {
+            let required = fields.iter().filter(|f| !f.optional);
+            let entries = required
+                .map(|f| format!("\"{}\": {}", f.name, f.name))
+                .collect::<Vec<_>>();
+            writeln!(
+                out,
+                "    kwargs: dict[str, Any] = {{{}}}",
+                entries.join(", ")
+            )
+            .unwrap();
+            // Omitted optional kwargs are left out, so that the Rust side applies its default.
+            for field in fields.iter().filter(|f| f.optional) {
+                writeln!(out, "    if {} is not None:", field.name).unwrap();
+                writeln!(out, "        kwargs[\"{}\"] = {}", field.name, field.name).unwrap();
+            }
+        }
+        Some(PluginKwargs::Opaque) | None => {}
+    }
+
+    out.push_str("    return register_plugin_function(\n");
+    out.push_str("        plugin_path=LIB,\n");
+    writeln!(out, "        args=[{args}],").unwrap();
+    writeln!(out, "        function_name=\"{}\",", function.name).unwrap();
+    for (flag, value) in &function.flags {
+        let value = if *value { "True" } else { "False" };
+        writeln!(out, "        {flag}={value},").unwrap();
+    }
+    if function.kwargs.is_some() {
+        out.push_str("        kwargs=kwargs,\n");
+    }
+    out.push_str("    )\n");
+}
+
+fn write_docstring(out: &mut String, indent: &str, doc: &[String], kwargs: Option<&PluginKwargs>) {
+    let fields = match kwargs {
+        Some(PluginKwargs::Struct(fields)) => fields.as_slice(),
+        _ => &[],
+    };
+    let documented = fields
+        .iter()
+        .filter(|f| !f.doc.is_empty())
+        .collect::<Vec<&KwargsField>>();
+    if doc.is_empty() && documented.is_empty() {
+        return;
+    }
+
+    writeln!(out, "{indent}\"\"\"").unwrap();
+    for line in doc {
+        writeln!(out, "{indent}{line}").unwrap();
+    }
+    if !documented.is_empty() {
+        if !doc.is_empty() {
+            out.push('\n');
+        }
+        writeln!(out, "{indent}Parameters\n{indent}----------").unwrap();
+        for field in documented {
+            writeln!(out, "{indent}{}", field.name).unwrap();
+            for line in &field.doc {
+                writeln!(out, "{indent}    {line}").unwrap();
+            }
+        }
+    }
+    writeln!(out, "{indent}\"\"\"").unwrap();
+}
+
+fn write_namespaces(out: &mut String, functions: &[PluginFunction], stub: bool) {
+    let mut namespaces: Vec<&str> = Vec::new();
+    for namespace in functions.iter().filter_map(|f| f.namespace.as_deref()) {
+        if !namespaces.contains(&namespace) {
+            namespaces.push(namespace)
+        }
+    }
+
+    for namespace in namespaces {
+        let class = class_name(namespace);
+        out.push_str("\n\n");
+        if !stub {
+            writeln!(out, "@pl.api.register_expr_namespace(\"{namespace}\")").unwrap();
+        }
+        writeln!(out, "class {class}:").unwrap();
+        if stub {
+            out.push_str("    def __init__(self, expr: pl.Expr) -> None: ...\n");
+        } else {
+            out.push_str("    def __init__(self, expr: pl.Expr) -> None:\n");
+            out.push_str("        self._expr = expr\n");
+        }
+
+        for function in functions
+            .iter()
+            .filter(|f| f.namespace.as_deref() == Some(namespace))
+        {
+            let signature = Signature::new(function);
+            let mut params = vec!["self".to_string()];
+            params.extend(signature.params(1));
+            let params = params.join(", ");
+            if stub {
+                writeln!(out, "    def {}({params}) -> pl.Expr: ...", function.name).unwrap();
+            } else {
+                let mut forward = vec!["self._expr".to_string()];
+                forward.extend(signature.forward(1));
+                writeln!(out, "\n    def {}({params}) -> pl.Expr:", function.name).unwrap();
+                write_docstring(out, "        ", &function.doc, function.kwargs.as_ref());
+                writeln!(
+                    out,
+                    "        return {}({})",
+                    function.name,
+                    forward.join(", ")
+                )
+                .unwrap();
+            }
+        }
+    }
+}
+
+/// `date_util` -> `DateUtilNamespace`
+fn class_name(namespace: &str) -> String {
+    let mut class = namespace
+        .split('_')
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect::<String>();
+    class.push_str("Namespace");
+    class
+}
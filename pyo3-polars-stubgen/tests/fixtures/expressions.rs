@@ -0,0 +1,72 @@
+use polars::prelude::*;
+use pyo3_polars::derive::{
+    polars_aggregation, polars_expr, Aggregation, CallerContext, DefaultKwargs, PluginState,
+};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct PigLatinKwargs {
+    /// Capitalize the output.
+    capitalize: bool,
+    #[serde(default, rename = "suffix")]
+    ending: String,
+}
+
+/// Convert to pig latin.
+#[polars_expr(output_type=String, is_elementwise=true, args=[expr], namespace=language)]
+fn pig_latinnify(inputs: &[Series], kwargs: PigLatinKwargs) -> PolarsResult<Series> {
+    todo!()
+}
+
+#[polars_expr(output_type=Float64, is_elementwise=true, cast_to_supertype=false, args=[expr, other])]
+fn jaccard_similarity(inputs: &[Series]) -> PolarsResult<Series> {
+    todo!()
+}
+
+#[polars_expr(output_type=String)]
+fn append(inputs: &[Series], kwargs: DefaultKwargs) -> PolarsResult<Series> {
+    todo!()
+}
+
+#[polars_expr(output_type=Boolean, args=[expr])]
+fn above(inputs: &[Series], threshold: f64, label: Option<&str>, context: CallerContext) -> PolarsResult<Series> {
+    todo!()
+}
+
+#[derive(Deserialize)]
+struct MatcherKwargs {
+    pattern: String,
+}
+
+struct Matcher;
+
+impl PluginState for Matcher {
+    type Kwargs = MatcherKwargs;
+}
+
+#[polars_expr(output_type=Boolean, namespace=language)]
+fn is_match(inputs: &[Series], state: &Matcher) -> PolarsResult<Series> {
+    todo!()
+}
+
+#[derive(Deserialize, Default)]
+struct TopKwargs {
+    k: usize,
+}
+
+/// The largest values.
+#[polars_aggregation(output_type=Int64, args=[expr])]
+struct TopValues;
+
+impl Aggregation for TopValues {
+    type Kwargs = TopKwargs;
+    type State = Vec<i64>;
+}
+
+#[polars_aggregation(output_type=Float64, name=geo_mean)]
+struct GeometricMean;
+
+impl Aggregation for GeometricMean {
+    type Kwargs = ();
+    type State = (f64, u64);
+}
@@ -0,0 +1,160 @@
+use pyo3_polars_stubgen::{Generator, PluginKwargs, StubgenError};
+
+fn generator() -> Generator {
+    Generator::new().source(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures"))
+}
+
+#[test]
+fn parses_functions() {
+    let functions = generator().functions().unwrap();
+    let names = functions
+        .iter()
+        .map(|f| f.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        [
+            "pig_latinnify",
+            "jaccard_similarity",
+            "append",
+            "above",
+            "is_match",
+            "top_values",
+            "geo_mean"
+        ]
+    );
+
+    let Some(PluginKwargs::Struct(fields)) = &functions[0].kwargs else {
+        panic!("expected kwargs struct")
+    };
+    assert_eq!(fields[0].name, "capitalize");
+    assert_eq!(fields[0].python_type, "bool");
+    assert_eq!(fields[0].doc, ["Capitalize the output."]);
+    assert!(!fields[0].optional);
+    assert_eq!(fields[1].name, "suffix");
+    assert!(fields[1].optional);
+
+    assert!(matches!(functions[2].kwargs, Some(PluginKwargs::Opaque)));
+
+    // `context` is neither a scalar nor a kwarg.
+    let scalars = functions[3]
+        .scalars
+        .iter()
+        .map(|s| (s.name.as_str(), s.python_type.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(scalars, [("threshold", "float"), ("label", "str | None")]);
+    assert!(functions[3].kwargs.is_none());
+
+    // The kwargs of `state` are those of its `PluginState` impl.
+    let Some(PluginKwargs::Struct(fields)) = &functions[4].kwargs else {
+        panic!("expected kwargs struct")
+    };
+    assert_eq!(fields[0].name, "pattern");
+
+    let Some(PluginKwargs::Struct(fields)) = &functions[5].kwargs else {
+        panic!("expected kwargs struct")
+    };
+    assert_eq!(fields[0].name, "k");
+    assert_eq!(functions[5].flags, [("returns_scalar".to_string(), true)]);
+    assert!(functions[6].kwargs.is_none());
+}
+
+#[test]
+fn renders_module() {
+    let generated = generator().generate().unwrap();
+    let module = generated.module;
+
+    assert!(module.contains(
+        "def pig_latinnify(expr: IntoExprColumn, *, capitalize: bool, suffix: str | None = None) -> pl.Expr:"
+    ));
+    assert!(module.contains("    kwargs: dict[str, Any] = {\"capitalize\": capitalize}\n"));
+    assert!(module.contains("        kwargs[\"suffix\"] = suffix\n"));
+    assert!(module.contains("        function_name=\"jaccard_similarity\",\n        is_elementwise=True,\n        cast_to_supertype=False,\n"));
+    assert!(module.contains(
+        "def append(expr: IntoExprColumn, *args: IntoExprColumn, **kwargs: Any) -> pl.Expr:"
+    ));
+    assert!(
+        module.contains("@pl.api.register_expr_namespace(\"language\")\nclass LanguageNamespace:")
+    );
+    assert!(module.contains(
+        "        return pig_latinnify(self._expr, capitalize=capitalize, suffix=suffix)"
+    ));
+
+    assert!(module.contains(
+        "def above(expr: IntoExprColumn, threshold: float, label: str | None) -> pl.Expr:"
+    ));
+    assert!(module.contains("        args=[expr, pl.lit(threshold), pl.lit(label)],\n"));
+    assert!(module.contains(
+        "def is_match(expr: IntoExprColumn, *args: IntoExprColumn, pattern: str) -> pl.Expr:"
+    ));
+    assert!(module.contains("    kwargs: dict[str, Any] = {\"pattern\": pattern}\n"));
+    assert!(module.contains("        return is_match(self._expr, *args, pattern=pattern)"));
+
+    let stubs = generated.stubs;
+    assert!(stubs.contains(
+        "def jaccard_similarity(expr: IntoExprColumn, other: IntoExprColumn) -> pl.Expr: ..."
+    ));
+    assert!(stubs.contains("    def pig_latinnify(self, *, capitalize: bool, suffix: str | None = None) -> pl.Expr: ..."));
+}
+
+fn generate_source(name: &str, source: &str) -> Result<(), StubgenError> {
+    let path = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join(format!("{name}.rs"));
+    std::fs::write(&path, source).unwrap();
+    Generator::new().source(path).generate().map(drop)
+}
+
+#[test]
+fn rejects_colliding_names() {
+    let kwargs = r#"
+        #[derive(Deserialize)]
+        struct Kwargs {
+            #[serde(rename = "args")]
+            extra: Vec<String>,
+        }
+
+        #[polars_expr(output_type=String)]
+        fn append(inputs: &[Series], kwargs: Kwargs) -> PolarsResult<Series> {
+            todo!()
+        }
+    "#;
+    let err = generate_source("kwargs_collision", kwargs).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "`append`: the parameter `args` collides with a name the generated Python function uses"
+    );
+
+    let scalar = r#"
+        #[derive(Deserialize)]
+        struct Kwargs {
+            threshold: f64,
+        }
+
+        #[polars_expr(output_type=Boolean, args=[expr])]
+        fn above(inputs: &[Series], threshold: f64, kwargs: Kwargs) -> PolarsResult<Series> {
+            todo!()
+        }
+    "#;
+    let err = generate_source("scalar_collision", scalar).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "`above`: the parameter `threshold` collides with another parameter"
+    );
+
+    let keyword = r#"
+        #[derive(Deserialize)]
+        struct Kwargs {
+            #[serde(rename = "from")]
+            start: i64,
+        }
+
+        #[polars_expr(output_type=Int64)]
+        fn shift(inputs: &[Series], kwargs: Kwargs) -> PolarsResult<Series> {
+            todo!()
+        }
+    "#;
+    let err = generate_source("keyword", keyword).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "`shift`: `from` is not a Python identifier"
+    );
+}
@@ -1,10 +1,18 @@
 use std::alloc::{GlobalAlloc, Layout, System};
-use std::ffi::c_char;
+#[cfg(feature = "alloc-stats")]
+use std::cell::Cell;
+use std::ffi::{c_char, c_void, CString};
+use std::fmt::{Display, Formatter};
+use std::os::raw::c_int;
+#[cfg(feature = "alloc-stats")]
+use std::sync::atomic::{AtomicIsize, AtomicU64, AtomicUsize, Ordering};
 use std::sync::OnceLock;
 
 // use once_cell::race::OnceRef;
-use pyo3::ffi::{PyCapsule_Import, Py_IsInitialized};
-use pyo3::Python;
+use pyo3::ffi::{PyCapsule_Import, PyErr_Clear, Py_AddPendingCall, Py_IsInitialized};
+use pyo3::prelude::*;
+#[cfg(feature = "alloc-stats")]
+use pyo3::types::PyDict;
 
 unsafe extern "C" fn fallback_alloc(size: usize, align: usize) -> *mut u8 {
     System.alloc(Layout::from_size_align_unchecked(size, align))
@@ -48,6 +56,316 @@ static FALLBACK_ALLOCATOR_CAPSULE: AllocatorCapsule = AllocatorCapsule {
 
 static ALLOCATOR_CAPSULE_NAME: &[u8] = b"polars.polars._allocator\0";
 
+/// The capsule of a [`VersionedAllocatorCapsule`], preferred over the unversioned one.
+///
+/// Forward-looking: the header is a layout proposed by this crate, and no polars release exports
+/// this capsule yet. Until one does, the lookup fails and the unversioned capsule is used.
+static VERSIONED_ALLOCATOR_CAPSULE_NAME: &[u8] = b"polars.polars._versioned_allocator\0";
+
+const ALLOCATOR_CAPSULE_MAGIC: [u8; 8] = *b"PLALLOC\0";
+/// The version of the allocator table this crate understands.
+const ALLOCATOR_CAPSULE_VERSION: u32 = 1;
+
+/// Describes the allocator table that follows it, so its layout can be validated before use.
+#[repr(C)]
+struct AllocatorCapsuleHeader {
+    /// [`ALLOCATOR_CAPSULE_MAGIC`]
+    magic: [u8; 8],
+    /// Bumped when the table changes incompatibly.
+    version: u32,
+    /// The size of the header and the table, tables may grow at the end within a version.
+    size: u32,
+}
+
+#[repr(C)]
+struct VersionedAllocatorCapsule {
+    header: AllocatorCapsuleHeader,
+    table: AllocatorCapsule,
+}
+
+impl AllocatorCapsuleHeader {
+    fn validate(&self) -> Result<u32, FallbackReason> {
+        if self.magic != ALLOCATOR_CAPSULE_MAGIC {
+            return Err(FallbackReason::InvalidHeader);
+        }
+        if self.version != ALLOCATOR_CAPSULE_VERSION {
+            return Err(FallbackReason::UnsupportedVersion(self.version));
+        }
+        if (self.size as usize) < size_of::<VersionedAllocatorCapsule>() {
+            return Err(FallbackReason::InvalidHeader);
+        }
+        Ok(self.version)
+    }
+}
+
+/// The allocator a [`PolarsAllocator`] relays to, see [`PolarsAllocator::status`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AllocatorStatus {
+    /// The allocator of polars, from a capsule whose header was validated. Forward-looking, as no
+    /// polars release exports a versioned capsule yet.
+    Polars {
+        /// The version of the capsule.
+        version: u32,
+    },
+    /// The allocator of polars, from the unversioned capsule `polars.polars._allocator` that polars
+    /// releases export. Its layout is trusted.
+    PolarsUnversioned,
+    /// [`std::alloc::System`], as the allocator of polars can't be used.
+    Fallback(FallbackReason),
+}
+
+/// Why a [`PolarsAllocator`] falls back to [`std::alloc::System`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FallbackReason {
+    /// The library was used outside of a Python interpreter.
+    PythonNotInitialized,
+    /// `polars.polars` doesn't export an allocator capsule, or can't be imported.
+    CapsuleNotFound,
+    /// The capsule doesn't start with a valid header.
+    InvalidHeader,
+    /// The capsule has a version this crate doesn't know.
+    UnsupportedVersion(u32),
+}
+
+impl Display for FallbackReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::PythonNotInitialized => write!(f, "Python is not initialized"),
+            Self::CapsuleNotFound => write!(f, "polars exports no allocator capsule"),
+            Self::InvalidHeader => write!(f, "the allocator capsule has an invalid header"),
+            Self::UnsupportedVersion(version) => write!(
+                f,
+                "the allocator capsule has version {version}, expected {ALLOCATOR_CAPSULE_VERSION}"
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Backend {
+    capsule: &'static AllocatorCapsule,
+    status: AllocatorStatus,
+}
+
+impl Backend {
+    fn fallback(reason: FallbackReason) -> Self {
+        Backend {
+            capsule: &FALLBACK_ALLOCATOR_CAPSULE,
+            status: AllocatorStatus::Fallback(reason),
+        }
+    }
+}
+
+/// Imports the allocator capsule of polars, preferring the versioned one.
+///
+/// # Safety
+/// The GIL must be held. Must not allocate.
+unsafe fn import_backend() -> Backend {
+    let versioned = PyCapsule_Import(
+        VERSIONED_ALLOCATOR_CAPSULE_NAME.as_ptr() as *const c_char,
+        0,
+    ) as *const VersionedAllocatorCapsule;
+    if let Some(capsule) = versioned.as_ref() {
+        return match capsule.header.validate() {
+            Ok(version) => Backend {
+                capsule: &capsule.table,
+                status: AllocatorStatus::Polars { version },
+            },
+            Err(reason) => Backend::fallback(reason),
+        };
+    }
+    PyErr_Clear();
+
+    let unversioned = PyCapsule_Import(ALLOCATOR_CAPSULE_NAME.as_ptr() as *const c_char, 0)
+        as *const AllocatorCapsule;
+    match unversioned.as_ref() {
+        Some(capsule) => Backend {
+            capsule,
+            status: AllocatorStatus::PolarsUnversioned,
+        },
+        None => {
+            PyErr_Clear();
+            Backend::fallback(FallbackReason::CapsuleNotFound)
+        }
+    }
+}
+
+/// The reason of the first fallback, set when it is reported.
+static FALLBACK_REASON: OnceLock<FallbackReason> = OnceLock::new();
+
+/// Warns once about the fallback, the next time the interpreter runs Python code, as the
+/// allocator must not allocate.
+fn schedule_fallback_warning(reason: FallbackReason) {
+    if FALLBACK_REASON.set(reason).is_ok() {
+        unsafe { Py_AddPendingCall(Some(warn_fallback), std::ptr::null_mut()) };
+    }
+}
+
+extern "C" fn warn_fallback(_arg: *mut c_void) -> c_int {
+    let Some(reason) = FALLBACK_REASON.get() else {
+        return 0;
+    };
+    Python::with_gil(|py| {
+        // Without polars, nothing is passed between allocators.
+        let polars_loaded = py
+            .import("sys")
+            .and_then(|sys| sys.getattr("modules"))
+            .and_then(|modules| modules.contains("polars"))
+            .unwrap_or(false);
+        if !polars_loaded {
+            return;
+        }
+        let msg = format!(
+            "pyo3-polars falls back to the system allocator, as {reason}. Memory passed between \
+             polars and this library may be freed by the wrong allocator."
+        );
+        let warned = CString::new(msg).map_err(PyErr::from).and_then(|msg| {
+            PyErr::warn(py, crate::PolarsWarning::Polars.class(py).as_any(), &msg, 0)
+        });
+        // A failing warning filter shouldn't raise in unrelated Python code.
+        if let Err(err) = warned {
+            err.write_unraisable(py, None);
+        }
+    });
+    0
+}
+
+// There is one global allocator per library, so its counters are statics that can be read without
+// a reference to it, e.g. from FFI. They are split into shards, one per thread unless there are more
+// threads than shards, so threads allocating in parallel don't contend, and summed when read.
+#[cfg(feature = "alloc-stats")]
+const SHARDS: usize = 64;
+/// The live bytes a shard collects before adding them to `LIVE_BYTES`, which the peak and the
+/// memory budgets are based on.
+#[cfg(feature = "alloc-stats")]
+const LIVE_BYTES_BATCH: isize = 64 * 1024;
+
+#[cfg(feature = "alloc-stats")]
+#[repr(align(128))]
+struct Shard {
+    /// The live bytes not added to `LIVE_BYTES` yet, negative if more were freed.
+    live_bytes: AtomicIsize,
+    allocations: AtomicU64,
+    reallocations: AtomicU64,
+    frees: AtomicU64,
+}
+
+#[cfg(feature = "alloc-stats")]
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY_SHARD: Shard = Shard {
+    live_bytes: AtomicIsize::new(0),
+    allocations: AtomicU64::new(0),
+    reallocations: AtomicU64::new(0),
+    frees: AtomicU64::new(0),
+};
+
+#[cfg(feature = "alloc-stats")]
+static SHARD_COUNTERS: [Shard; SHARDS] = [EMPTY_SHARD; SHARDS];
+#[cfg(feature = "alloc-stats")]
+static NEXT_SHARD: AtomicUsize = AtomicUsize::new(0);
+/// Signed, as a shard may add a free before another adds the allocation.
+#[cfg(feature = "alloc-stats")]
+static LIVE_BYTES: AtomicIsize = AtomicIsize::new(0);
+#[cfg(feature = "alloc-stats")]
+static PEAK_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+/// Serializes the tests allocating through a `PolarsAllocator`, whose counters are shared.
+#[cfg(all(test, feature = "alloc-stats"))]
+pub(crate) static ALLOCATOR_TESTS: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+#[cfg(feature = "alloc-stats")]
+thread_local! {
+    // Const initialized without a destructor, so accessing it doesn't allocate.
+    static SHARD: Cell<usize> = const { Cell::new(usize::MAX) };
+}
+
+/// The shard of this thread.
+#[cfg(feature = "alloc-stats")]
+#[inline]
+fn shard() -> &'static Shard {
+    let index = SHARD
+        .try_with(|shard| {
+            if shard.get() == usize::MAX {
+                shard.set(NEXT_SHARD.fetch_add(1, Ordering::Relaxed) % SHARDS);
+            }
+            shard.get()
+        })
+        .unwrap_or(0);
+    &SHARD_COUNTERS[index]
+}
+
+/// The allocations made through the [`PolarsAllocator`] of this library, see
+/// [`PolarsAllocator::stats`].
+///
+/// In a `#[pyfunction]` they convert to a `dict` with the same keys.
+#[cfg(feature = "alloc-stats")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AllocatorStats {
+    /// The bytes allocated and not freed yet.
+    pub live_bytes: usize,
+    /// The highest `live_bytes` since the last reset.
+    pub peak_bytes: usize,
+    /// The number of allocations.
+    pub allocations: u64,
+    /// The number of reallocations, which change `live_bytes` by the difference in size.
+    pub reallocations: u64,
+    /// The number of frees.
+    pub frees: u64,
+}
+
+#[cfg(feature = "alloc-stats")]
+impl<'py> IntoPyObject<'py> for AllocatorStats {
+    type Target = PyDict;
+    type Output = Bound<'py, PyDict>;
+    type Error = PyErr;
+
+    fn into_pyobject(self, py: Python<'py>) -> Result<Self::Output, Self::Error> {
+        let dict = PyDict::new(py);
+        dict.set_item("live_bytes", self.live_bytes)?;
+        dict.set_item("peak_bytes", self.peak_bytes)?;
+        dict.set_item("allocations", self.allocations)?;
+        dict.set_item("reallocations", self.reallocations)?;
+        dict.set_item("frees", self.frees)?;
+        Ok(dict)
+    }
+}
+
+// The counters are updated with atomics only, the allocator must not allocate.
+#[cfg(feature = "alloc-stats")]
+#[inline]
+fn record_live_bytes(shard: &Shard, delta: isize) {
+    let pending = shard.live_bytes.fetch_add(delta, Ordering::Relaxed) + delta;
+    if pending.abs() < LIVE_BYTES_BATCH {
+        return;
+    }
+    let pending = shard.live_bytes.swap(0, Ordering::Relaxed);
+    let live = LIVE_BYTES.fetch_add(pending, Ordering::Relaxed) + pending;
+    if pending > 0 {
+        let live = live.max(0) as usize;
+        PEAK_BYTES.fetch_max(live, Ordering::Relaxed);
+        #[cfg(feature = "memory-budget")]
+        crate::memory_budget::record_live_bytes(live);
+    }
+}
+
+/// The live bytes, including those the shards haven't added to `LIVE_BYTES` yet.
+#[cfg(feature = "alloc-stats")]
+fn live_bytes() -> usize {
+    let pending: isize = SHARD_COUNTERS
+        .iter()
+        .map(|shard| shard.live_bytes.load(Ordering::Relaxed))
+        .sum();
+    (LIVE_BYTES.load(Ordering::Relaxed) + pending).max(0) as usize
+}
+
+#[cfg(feature = "alloc-stats")]
+fn sum(counter: impl Fn(&Shard) -> &AtomicU64) -> u64 {
+    SHARD_COUNTERS
+        .iter()
+        .map(|shard| counter(shard).load(Ordering::Relaxed))
+        .sum()
+}
+
 /// A memory allocator that relays allocations to the allocator used by Polars.
 ///
 /// You can use it as the global memory allocator:
@@ -60,25 +378,40 @@ static ALLOCATOR_CAPSULE_NAME: &[u8] = b"polars.polars._allocator\0";
 /// ```
 ///
 /// If the allocator capsule (`polars.polars._allocator`) is not available,
-/// this allocator fallbacks to [`std::alloc::System`].
-pub struct PolarsAllocator(OnceLock<&'static AllocatorCapsule>);
+/// this allocator fallbacks to [`std::alloc::System`]. A capsule with a header
+/// (`polars.polars._versioned_allocator`) is preferred and only used if the header is valid.
+/// That capsule is forward-looking: no polars release exports it yet, so for now the unversioned
+/// capsule is used without validation and its layout is trusted.
+/// [`PolarsAllocator::status`] tells which allocator is used, and a fallback in a process that
+/// loaded polars is reported once with a `PolarsWarning`.
+///
+/// With the `alloc-stats` feature, it counts the allocations of the library, see
+/// [`PolarsAllocator::stats`].
+pub struct PolarsAllocator(OnceLock<Backend>);
 
 impl PolarsAllocator {
     fn get_allocator(&self) -> &'static AllocatorCapsule {
+        self.backend().capsule
+    }
+
+    fn backend(&self) -> &Backend {
         // Do not allocate in this function,
         // otherwise it will cause infinite recursion.
         self.0.get_or_init(|| {
-            let r = (unsafe { Py_IsInitialized() } != 0)
-                .then(|| {
-                    Python::with_gil(|_| unsafe {
-                        (PyCapsule_Import(ALLOCATOR_CAPSULE_NAME.as_ptr() as *const c_char, 0)
-                            as *const AllocatorCapsule)
-                            .as_ref()
-                    })
-                })
-                .flatten();
+            let python = unsafe { Py_IsInitialized() } != 0;
+            let backend = if python {
+                Python::with_gil(|_| unsafe { import_backend() })
+            } else {
+                Backend::fallback(FallbackReason::PythonNotInitialized)
+            };
+            let AllocatorStatus::Fallback(reason) = backend.status else {
+                return backend;
+            };
+            if python {
+                schedule_fallback_warning(reason);
+            }
             #[cfg(debug_assertions)]
-            if r.is_none() {
+            {
                 // Do not use eprintln; it may alloc.
                 let msg = b"failed to get allocator capsule\n";
                 // Message length type is platform-dependent.
@@ -87,14 +420,50 @@ impl PolarsAllocator {
                 let msg_len = msg.len().try_into().unwrap();
                 unsafe { libc::write(2, msg.as_ptr() as *const libc::c_void, msg_len) };
             }
-            r.unwrap_or(&FALLBACK_ALLOCATOR_CAPSULE)
+            backend
         })
     }
 
+    /// The allocator this one relays to, chosen on the first allocation.
+    pub fn status(&self) -> AllocatorStatus {
+        self.backend().status
+    }
+
     /// Create a `PolarsAllocator`.
     pub const fn new() -> Self {
         PolarsAllocator(OnceLock::new())
     }
+
+    /// The allocations made through the `PolarsAllocator` of this library so far.
+    ///
+    /// To see what a call allocates, compare the stats before and after it, or call
+    /// [`PolarsAllocator::reset_stats`] first to get its peak. The counters are summed over all
+    /// threads, so calls running in parallel are counted together.
+    ///
+    /// The counts and live bytes are exact. Threads add their live bytes to the total the peak is
+    /// taken from in batches of 64 KiB, so the peak may miss up to that much per thread.
+    #[cfg(feature = "alloc-stats")]
+    pub fn stats() -> AllocatorStats {
+        let live_bytes = live_bytes();
+        AllocatorStats {
+            live_bytes,
+            peak_bytes: PEAK_BYTES.load(Ordering::Relaxed).max(live_bytes),
+            allocations: sum(|shard| &shard.allocations),
+            reallocations: sum(|shard| &shard.reallocations),
+            frees: sum(|shard| &shard.frees),
+        }
+    }
+
+    /// Sets the counts back to zero and the peak to the bytes that are live now.
+    #[cfg(feature = "alloc-stats")]
+    pub fn reset_stats() {
+        for shard in &SHARD_COUNTERS {
+            shard.allocations.store(0, Ordering::Relaxed);
+            shard.reallocations.store(0, Ordering::Relaxed);
+            shard.frees.store(0, Ordering::Relaxed);
+        }
+        PEAK_BYTES.store(live_bytes(), Ordering::Relaxed);
+    }
 }
 
 impl Default for PolarsAllocator {
@@ -106,21 +475,176 @@ impl Default for PolarsAllocator {
 unsafe impl GlobalAlloc for PolarsAllocator {
     #[inline]
     unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
-        (self.get_allocator().alloc)(layout.size(), layout.align())
+        let ptr = (self.get_allocator().alloc)(layout.size(), layout.align());
+        #[cfg(feature = "alloc-stats")]
+        if !ptr.is_null() {
+            let shard = shard();
+            shard.allocations.fetch_add(1, Ordering::Relaxed);
+            record_live_bytes(shard, layout.size() as isize);
+        }
+        ptr
     }
 
     #[inline]
     unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
         (self.get_allocator().dealloc)(ptr, layout.size(), layout.align());
+        #[cfg(feature = "alloc-stats")]
+        {
+            let shard = shard();
+            shard.frees.fetch_add(1, Ordering::Relaxed);
+            record_live_bytes(shard, -(layout.size() as isize));
+        }
     }
 
     #[inline]
     unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
-        (self.get_allocator().alloc_zeroed)(layout.size(), layout.align())
+        let ptr = (self.get_allocator().alloc_zeroed)(layout.size(), layout.align());
+        #[cfg(feature = "alloc-stats")]
+        if !ptr.is_null() {
+            let shard = shard();
+            shard.allocations.fetch_add(1, Ordering::Relaxed);
+            record_live_bytes(shard, layout.size() as isize);
+        }
+        ptr
     }
 
     #[inline]
     unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
-        (self.get_allocator().realloc)(ptr, layout.size(), layout.align(), new_size)
+        let new_ptr = (self.get_allocator().realloc)(ptr, layout.size(), layout.align(), new_size);
+        #[cfg(feature = "alloc-stats")]
+        if !new_ptr.is_null() {
+            let shard = shard();
+            shard.reallocations.fetch_add(1, Ordering::Relaxed);
+            record_live_bytes(shard, new_size as isize - layout.size() as isize);
+        }
+        new_ptr
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pyo3::ffi::PyCapsule_New;
+    use pyo3::types::PyDict;
+
+    use super::*;
+
+    const SIZE: usize = size_of::<VersionedAllocatorCapsule>();
+
+    fn header(magic: [u8; 8], version: u32, size: usize) -> AllocatorCapsuleHeader {
+        AllocatorCapsuleHeader {
+            magic,
+            version,
+            size: size as u32,
+        }
+    }
+
+    #[test]
+    fn validate_accepts_known_header() {
+        let valid = header(ALLOCATOR_CAPSULE_MAGIC, ALLOCATOR_CAPSULE_VERSION, SIZE);
+        assert_eq!(valid.validate(), Ok(ALLOCATOR_CAPSULE_VERSION));
+        // The table may grow at the end within a version.
+        let grown = header(ALLOCATOR_CAPSULE_MAGIC, ALLOCATOR_CAPSULE_VERSION, SIZE + 8);
+        assert_eq!(grown.validate(), Ok(ALLOCATOR_CAPSULE_VERSION));
+    }
+
+    #[test]
+    fn validate_rejects_bad_magic() {
+        let bad = header(*b"PLALLOX\0", ALLOCATOR_CAPSULE_VERSION, SIZE);
+        assert_eq!(bad.validate(), Err(FallbackReason::InvalidHeader));
+    }
+
+    #[test]
+    fn validate_rejects_unknown_version() {
+        let version = ALLOCATOR_CAPSULE_VERSION + 1;
+        let bad = header(ALLOCATOR_CAPSULE_MAGIC, version, SIZE);
+        assert_eq!(
+            bad.validate(),
+            Err(FallbackReason::UnsupportedVersion(version))
+        );
+    }
+
+    #[test]
+    fn validate_rejects_short_size() {
+        let bad = header(ALLOCATOR_CAPSULE_MAGIC, ALLOCATOR_CAPSULE_VERSION, SIZE - 1);
+        assert_eq!(bad.validate(), Err(FallbackReason::InvalidHeader));
+    }
+
+    /// The table polars exports as `polars.polars._allocator`.
+    static HOST_CAPSULE: AllocatorCapsule = AllocatorCapsule {
+        alloc: fallback_alloc,
+        dealloc: fallback_dealloc,
+        alloc_zeroed: fallback_alloc_zeroed,
+        realloc: fallback_realloc,
+    };
+
+    #[test]
+    fn imports_the_capsule_of_polars() {
+        Python::with_gil(|py| {
+            let host = py.import("polars").is_ok();
+            if !host {
+                // Without polars installed, a stand-in exports the capsule the way polars does.
+                let capsule = unsafe {
+                    Bound::from_owned_ptr(
+                        py,
+                        PyCapsule_New(
+                            &HOST_CAPSULE as *const AllocatorCapsule as *mut c_void,
+                            ALLOCATOR_CAPSULE_NAME.as_ptr() as *const c_char,
+                            None,
+                        ),
+                    )
+                };
+                let locals = PyDict::new(py);
+                locals.set_item("capsule", capsule).unwrap();
+                py.run(
+                    c"import sys, types
+polars = types.ModuleType('polars')
+polars.polars = types.ModuleType('polars.polars')
+polars.polars._allocator = capsule
+sys.modules['polars'] = polars",
+                    None,
+                    Some(&locals),
+                )
+                .unwrap();
+            }
+            let backend = unsafe { import_backend() };
+            if !host {
+                py.run(c"import sys; del sys.modules['polars']", None, None)
+                    .unwrap();
+                assert!(std::ptr::eq(backend.capsule, &HOST_CAPSULE));
+            }
+
+            // No polars release exports the versioned capsule yet.
+            assert_eq!(backend.status, AllocatorStatus::PolarsUnversioned);
+            unsafe {
+                let ptr = (backend.capsule.alloc)(64, 8);
+                assert!(!ptr.is_null());
+                let ptr = (backend.capsule.realloc)(ptr, 64, 8, 128);
+                assert!(!ptr.is_null());
+                (backend.capsule.dealloc)(ptr, 128, 8);
+            }
+        });
+    }
+
+    #[cfg(feature = "alloc-stats")]
+    #[test]
+    fn counts_allocations() {
+        let _lock = ALLOCATOR_TESTS.lock().unwrap();
+        // Not the global allocator of the tests, so only these tests allocate through it.
+        let alloc = PolarsAllocator::new();
+        // Above the 64 KiB the threads batch their live bytes in, so the peak sees them.
+        let layout = Layout::from_size_align(1 << 20, 8).unwrap();
+        let before = PolarsAllocator::stats();
+        unsafe {
+            let ptr = alloc.alloc(layout);
+            let ptr = alloc.realloc(ptr, layout, 4 << 20);
+            alloc.dealloc(ptr, Layout::from_size_align(4 << 20, 8).unwrap());
+        }
+
+        let after = PolarsAllocator::stats();
+        assert_eq!(after.allocations - before.allocations, 1);
+        assert_eq!(after.reallocations - before.reallocations, 1);
+        assert_eq!(after.frees - before.frees, 1);
+        assert_eq!(after.live_bytes, before.live_bytes);
+        assert!(after.peak_bytes >= 4 << 20);
     }
 }
@@ -1,18 +1,76 @@
 //! Utilities to deriving and work with Polars expressions in a Python context.
 //!
-//! Includes functions to deserialize pickled kwargs, update error messages, and set panic messages.
+//! Includes functions to deserialize kwargs, update error messages, and set panic messages. Kwargs
+//! are pickled by Python, other hosts can also pass JSON, CBOR or MessagePack with the
+//! `kwargs-json`, `kwargs-cbor` and `kwargs-msgpack` features, see `KwargsEncoding`.
 //!
-//! Provides FFI functions to get the last error message and the plugin version.
+//! Provides FFI functions to get the last error message, its kind and context, and the plugin
+//! version, see `ErrorKind`.
 //!
-//! Sets up a custom panic hook to only show output if `POLARS_VERBOSE` environment variable is "1".
+//! Plugins can run setup code once with `#[polars_plugin_init]` and reuse state built from the
+//! kwargs, see `PluginState`.
+//!
+//! Output type functions may take a `context` and kwargs, and return a `Field` or a `DataType`,
+//! see `IntoOutputField`.
+//!
+//! Literal arguments can be taken as Rust values, see `ScalarArg`.
+//!
+//! Expression functions generic over the dtype of their inputs are instantiated with
+//! `#[polars_expr(dispatch = [..])]`.
+//!
+//! With the `stats` feature, expression functions record call statistics, exported through
+//! `_polars_plugin_stats`.
+//!
+//! Custom aggregations are defined by implementing `Aggregation`.
+//!
+//! With the `dtype-struct` feature, expressions can return several columns at once as a `Struct`,
+//! see `StructOutput`.
+//!
+//! Sets up a panic hook that records the message and location of panics inside plugin calls as the
+//! last error, and only shows their output if `POLARS_VERBOSE` environment variable is "1". It can
+//! be disabled or customized, see `disable_panic_hook`.
 use polars::prelude::PolarsError;
-use polars_core::error::{to_compute_err, PolarsResult};
-pub use pyo3_polars_derive::polars_expr;
-use serde::Deserialize;
-use std::cell::RefCell;
+#[cfg(feature = "dtype-struct")]
+pub use pyo3_polars_derive::StructOutput;
+pub use pyo3_polars_derive::{polars_aggregation, polars_expr, polars_plugin_init, KwargsSchema};
+use std::any::Any;
+use std::backtrace::{Backtrace, BacktraceStatus};
+use std::cell::{Cell, RefCell};
 use std::ffi::CString;
+use std::panic::PanicHookInfo;
 use std::sync::atomic::{AtomicBool, Ordering};
 
+mod aggregation;
+mod dispatch;
+mod error_kind;
+mod import;
+mod kwargs;
+mod lifecycle;
+mod output_type;
+mod panic_hook;
+mod scalar;
+mod stats;
+#[cfg(feature = "dtype-struct")]
+mod struct_output;
+pub mod testing;
+pub use aggregation::*;
+pub use dispatch::*;
+pub use error_kind::*;
+pub use import::*;
+pub use kwargs::*;
+pub use lifecycle::*;
+pub use output_type::*;
+pub(crate) use panic_hook::in_plugin_call;
+pub use panic_hook::*;
+pub use scalar::*;
+pub use stats::*;
+#[cfg(feature = "dtype-struct")]
+pub use struct_output::*;
+
+// Used by `#[polars_plugin_init]`, so plugins don't depend on it.
+#[doc(hidden)]
+pub use inventory;
+
 /// Gives the caller extra information on how to execute the expression.
 pub use polars_ffi::version_0::CallerContext;
 
@@ -21,28 +79,78 @@ pub type DefaultKwargs = serde_pickle::Value;
 
 thread_local! {
     static LAST_ERROR: RefCell<CString> = RefCell::new(CString::default());
-}
-
-/// deserializes a pickled kwargs object
-pub fn _parse_kwargs<'a, T>(kwargs: &'a [u8]) -> PolarsResult<T>
-where
-    T: Deserialize<'a>,
-{
-    serde_pickle::from_slice(kwargs, Default::default()).map_err(to_compute_err)
+    static LAST_ERROR_KIND: Cell<ErrorKind> = const { Cell::new(ErrorKind::None) };
+    /// The context messages of the last error, separated by newlines.
+    static LAST_ERROR_CONTEXT: RefCell<CString> = RefCell::new(CString::default());
+    /// The message of the last panic on this thread, recorded by the panic hook.
+    static LAST_PANIC: RefCell<Option<String>> = const { RefCell::new(None) };
 }
 
 /// sets the error message in the thread-local error object
 pub fn _update_last_error(err: PolarsError) {
-    let msg = format!("{err}");
-    let msg = CString::new(msg).unwrap();
-    LAST_ERROR.with(|prev| *prev.borrow_mut() = msg)
+    set_last_error(
+        ErrorKind::of(&err),
+        &error_context(&err).join("\n"),
+        format!("{err}"),
+    )
 }
 
 /// sets a panic message in the thread-local error object
+///
+/// Uses the message recorded by the panic hook if there is one, otherwise falls back to `"PANIC"`.
 pub fn _set_panic() {
-    let msg = "PANIC";
-    let msg = CString::new(msg).unwrap();
-    LAST_ERROR.with(|prev| *prev.borrow_mut() = msg)
+    let msg = LAST_PANIC
+        .with(|prev| prev.borrow_mut().take())
+        .unwrap_or_else(|| "PANIC".to_string());
+    set_last_error(ErrorKind::Panic, "", msg)
+}
+
+/// sets the message of a caught panic in the thread-local error object
+///
+/// The message recorded by the panic hook is preferred as it has the location and backtrace. The
+/// payload is used if the hook didn't run on this thread, e.g. because another hook replaced ours
+/// or the panic happened on a different thread and was resumed here.
+pub fn _set_panic_payload(payload: &(dyn Any + Send)) {
+    let msg = LAST_PANIC
+        .with(|prev| prev.borrow_mut().take())
+        .unwrap_or_else(|| format!("panicked:\n{}", payload_message(payload)));
+    set_last_error(ErrorKind::Panic, "", msg)
+}
+
+fn set_last_error(kind: ErrorKind, context: &str, msg: String) {
+    LAST_ERROR_KIND.with(|prev| prev.set(kind));
+    LAST_ERROR_CONTEXT.with(|prev| *prev.borrow_mut() = to_c_string(context));
+    LAST_ERROR.with(|prev| *prev.borrow_mut() = to_c_string(&msg))
+}
+
+fn to_c_string(msg: &str) -> CString {
+    // Interior nul bytes would make `CString::new` fail.
+    CString::new(msg.replace('\0', "\\0")).unwrap()
+}
+
+fn payload_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(msg) = payload.downcast_ref::<&str>() {
+        msg
+    } else if let Some(msg) = payload.downcast_ref::<String>() {
+        msg
+    } else {
+        "Box<dyn Any>"
+    }
+}
+
+/// Formats a panic the way the default hook does, with a backtrace if `RUST_BACKTRACE` is set.
+fn panic_message(info: &PanicHookInfo) -> String {
+    let mut msg = match info.location() {
+        Some(location) => format!("panicked at {location}:\n"),
+        None => "panicked:\n".to_string(),
+    };
+    msg.push_str(payload_message(info.payload()));
+
+    let backtrace = Backtrace::capture();
+    if backtrace.status() == BacktraceStatus::Captured {
+        msg.push_str(&format!("\n\nstack backtrace:\n{backtrace}"));
+    }
+    msg
 }
 
 #[no_mangle]
@@ -52,26 +160,56 @@ pub unsafe extern "C" fn _polars_plugin_get_last_error_message() -> *const std::
     LAST_ERROR.with(|prev| prev.borrow_mut().as_ptr())
 }
 
-static INIT: AtomicBool = AtomicBool::new(false);
+#[no_mangle]
+/// The `ErrorKind` code of the last error.
+///
+/// # Safety
+/// FFI function, so unsafe
+pub unsafe extern "C" fn _polars_plugin_get_last_error_kind() -> u32 {
+    LAST_ERROR_KIND.with(|prev| prev.get()).code()
+}
 
-fn start_up_init() {
-    // Set a custom panic hook that only shows output if verbose.
-    std::panic::set_hook(Box::new(|info| {
-        let show_message = std::env::var("POLARS_VERBOSE").as_deref().unwrap_or("") == "1";
-        if show_message {
-            eprintln!("{info}")
-        }
-    }));
+#[no_mangle]
+/// The context messages of the last error, outermost first and separated by newlines.
+///
+/// # Safety
+/// FFI function, so unsafe
+pub unsafe extern "C" fn _polars_plugin_get_last_error_context() -> *const std::os::raw::c_char {
+    LAST_ERROR_CONTEXT.with(|prev| prev.borrow_mut().as_ptr())
 }
 
 #[no_mangle]
+/// Resets the last error, so a call that fails without setting one isn't reported with the error
+/// of an earlier call.
+///
 /// # Safety
 /// FFI function, so unsafe
-pub unsafe extern "C" fn _polars_plugin_get_version() -> u32 {
+pub unsafe extern "C" fn _polars_plugin_clear_last_error() {
+    set_last_error(ErrorKind::None, "", String::new())
+}
+
+static INIT: AtomicBool = AtomicBool::new(false);
+
+fn start_up_init() {
+    panic_hook::install();
+
+    #[cfg(feature = "log")]
+    crate::logging::init();
+}
+
+/// Runs `start_up_init` once.
+fn ensure_start_up() {
     if !INIT.swap(true, Ordering::Relaxed) {
-        // Plugin version is is always called at least once.
         start_up_init();
     }
+}
+
+#[no_mangle]
+/// # Safety
+/// FFI function, so unsafe
+pub unsafe extern "C" fn _polars_plugin_get_version() -> u32 {
+    // Plugin version is is always called at least once.
+    ensure_start_up();
     let (major, minor) = polars_ffi::get_version();
     // Stack bits together
     ((major as u32) << 16) + minor as u32
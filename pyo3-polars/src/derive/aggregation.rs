@@ -0,0 +1,282 @@
+//! Aggregations that are computed from partial states, see [`Aggregation`].
+use std::ffi::c_void;
+use std::marker::PhantomData;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::Arc;
+
+use polars::prelude::*;
+use polars_ffi::version_0::{export_series, SeriesExport};
+use serde::de::DeserializeOwned;
+
+use super::{
+    KwargsSchema, PluginState, _PluginCall, _cached_state, _parse_kwargs,
+    _parse_kwargs_with_schema, _plugin_init, _set_panic_payload, _update_last_error,
+};
+
+/// A custom aggregation, computed by folding the input into a partial state.
+///
+/// Register it by putting `#[polars_aggregation]` on the type implementing this trait:
+///
+/// ```rust,ignore
+/// #[polars_aggregation(output_type=Float64)]
+/// struct GeometricMean;
+///
+/// impl Aggregation for GeometricMean {
+///     type Kwargs = ();
+///     type State = (f64, u64);
+///
+///     fn init(_kwargs: &()) -> PolarsResult<Self::State> {
+///         Ok((0.0, 0))
+///     }
+///
+///     fn update(state: &mut Self::State, inputs: &[Series]) -> PolarsResult<()> {
+///         let ca = inputs[0].f64()?;
+///         state.0 += ca.into_no_null_iter().map(f64::ln).sum::<f64>();
+///         state.1 += ca.len() as u64;
+///         Ok(())
+///     }
+///
+///     fn combine(state: &mut Self::State, other: Self::State) -> PolarsResult<()> {
+///         state.0 += other.0;
+///         state.1 += other.1;
+///         Ok(())
+///     }
+///
+///     fn finalize(state: Self::State) -> PolarsResult<Series> {
+///         let mean = (state.0 / state.1 as f64).exp();
+///         Ok(Series::new("".into(), [mean]))
+///     }
+/// }
+/// ```
+///
+/// This generates the usual expression symbols, `_polars_plugin_<name>` and
+/// `_polars_plugin_field_<name>`, so the aggregation can be registered with `returns_scalar=True`
+/// like any other expression. Polars calls the expression once per group, with all values of the
+/// group, so for now the generated expression calls `init`, `update` once with the whole group and
+/// `finalize`.
+///
+/// The partial states are also exposed, to let the aggregation run on chunks of a group and merge
+/// the results:
+///
+/// - `_polars_plugin_agg_init_<name>(kwargs_ptr, kwargs_len) -> *mut c_void`
+/// - `_polars_plugin_agg_update_<name>(state, series, len) -> bool`
+/// - `_polars_plugin_agg_combine_<name>(state, other) -> bool`, consumes `other`
+/// - `_polars_plugin_agg_finalize_<name>(state, return_value) -> bool`, consumes `state`
+/// - `_polars_plugin_agg_drop_<name>(state)`
+///
+/// Functions returning a null pointer or `false` have failed and set the last error. Passing them a
+/// null state fails as well, `_polars_plugin_agg_drop_<name>` then only sets the last error.
+///
+/// The kwargs are parsed as for `#[polars_expr]`, describing the expected kwargs on failure if they
+/// implement [`KwargsSchema`]. Kwargs implementing [`PluginState`] are built from their own kwargs
+/// and cached, others are deserialized and default to `Kwargs::default()` if none were passed.
+pub trait Aggregation {
+    /// The kwargs of the aggregation, deserialized or a [`PluginState`].
+    type Kwargs: Send + Sync + 'static;
+    /// The partial result of the aggregation.
+    type State: Send;
+
+    /// Create an empty state.
+    fn init(kwargs: &Self::Kwargs) -> PolarsResult<Self::State>;
+
+    /// Fold the inputs into the state.
+    fn update(state: &mut Self::State, inputs: &[Series]) -> PolarsResult<()>;
+
+    /// Merge two states that were updated with different parts of the input.
+    fn combine(state: &mut Self::State, other: Self::State) -> PolarsResult<()>;
+
+    /// Compute the output from the state.
+    fn finalize(state: Self::State) -> PolarsResult<Series>;
+}
+
+fn kwargs_error(err: PolarsError) -> PolarsError {
+    polars_err!(InvalidOperation: "could not parse kwargs: '{}'\n\nCheck: registration of kwargs in the plugin.", err)
+}
+
+/// Picks how the kwargs of an aggregation are parsed with autoref specialization, like
+/// [`_KwargsParser`](super::_KwargsParser) does for expressions:
+///
+/// ```rust,ignore
+/// use pyo3_polars::derive::{
+///     _GetAggregationKwargs, _GetAggregationKwargsWithSchema, _GetAggregationState,
+///     _GetAggregationStateWithSchema,
+/// };
+/// let getter = pyo3_polars::derive::_aggregation_kwargs::<MyAggregation>();
+/// let kwargs = (&&&&getter).get_kwargs(bytes)?;
+/// ```
+#[doc(hidden)]
+pub struct _AggregationKwargs<K>(PhantomData<K>);
+
+/// The kwargs getter of the aggregation `A`.
+#[doc(hidden)]
+pub fn _aggregation_kwargs<A: Aggregation>() -> _AggregationKwargs<A::Kwargs> {
+    _AggregationKwargs(PhantomData)
+}
+
+#[doc(hidden)]
+pub trait _GetAggregationStateWithSchema<K> {
+    fn get_kwargs(&self, kwargs: &[u8]) -> PolarsResult<Arc<K>>;
+}
+
+impl<K: PluginState> _GetAggregationStateWithSchema<K> for &&&_AggregationKwargs<K>
+where
+    K::Kwargs: KwargsSchema,
+{
+    fn get_kwargs(&self, kwargs: &[u8]) -> PolarsResult<Arc<K>> {
+        _cached_state::<K>(kwargs, |kwargs| {
+            _parse_kwargs_with_schema(kwargs).map_err(kwargs_error)
+        })
+    }
+}
+
+#[doc(hidden)]
+pub trait _GetAggregationState<K> {
+    fn get_kwargs(&self, kwargs: &[u8]) -> PolarsResult<Arc<K>>;
+}
+
+impl<K: PluginState> _GetAggregationState<K> for &&_AggregationKwargs<K> {
+    fn get_kwargs(&self, kwargs: &[u8]) -> PolarsResult<Arc<K>> {
+        _cached_state::<K>(kwargs, |kwargs| _parse_kwargs(kwargs).map_err(kwargs_error))
+    }
+}
+
+#[doc(hidden)]
+pub trait _GetAggregationKwargsWithSchema<K> {
+    fn get_kwargs(&self, kwargs: &[u8]) -> PolarsResult<Arc<K>>;
+}
+
+impl<K> _GetAggregationKwargsWithSchema<K> for &_AggregationKwargs<K>
+where
+    K: DeserializeOwned + Default + KwargsSchema,
+{
+    fn get_kwargs(&self, kwargs: &[u8]) -> PolarsResult<Arc<K>> {
+        if kwargs.is_empty() {
+            return Ok(Arc::default());
+        }
+        _parse_kwargs_with_schema(kwargs)
+            .map(Arc::new)
+            .map_err(kwargs_error)
+    }
+}
+
+#[doc(hidden)]
+pub trait _GetAggregationKwargs<K> {
+    fn get_kwargs(&self, kwargs: &[u8]) -> PolarsResult<Arc<K>>;
+}
+
+impl<K: DeserializeOwned + Default> _GetAggregationKwargs<K> for _AggregationKwargs<K> {
+    fn get_kwargs(&self, kwargs: &[u8]) -> PolarsResult<Arc<K>> {
+        if kwargs.is_empty() {
+            return Ok(Arc::default());
+        }
+        _parse_kwargs(kwargs).map(Arc::new).map_err(kwargs_error)
+    }
+}
+
+/// Runs the aggregation on a single group: `init`, `update` and `finalize`. `kwargs` is called
+/// once the plugin is initialized.
+#[doc(hidden)]
+pub fn _aggregate<A: Aggregation>(
+    inputs: &[Series],
+    kwargs: impl FnOnce() -> PolarsResult<Arc<A::Kwargs>>,
+) -> PolarsResult<Series> {
+    _plugin_init()?;
+    let mut state = A::init(&kwargs()?)?;
+    A::update(&mut state, inputs)?;
+    A::finalize(state)
+}
+
+/// Runs `f`, reporting errors and panics as the last error.
+fn ffi_call<T>(f: impl FnOnce() -> PolarsResult<T>) -> Option<T> {
+    let _plugin_call = _PluginCall::enter();
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(out)) => Some(out),
+        Ok(Err(err)) => {
+            _update_last_error(err);
+            None
+        }
+        Err(payload) => {
+            _set_panic_payload(&*payload);
+            None
+        }
+    }
+}
+
+/// # Safety
+/// `state` must be null or come from [`_aggregation_init`] of an aggregation with state `S`.
+unsafe fn state_mut<'a, S>(state: *mut c_void) -> PolarsResult<&'a mut S> {
+    polars_ensure!(!state.is_null(), InvalidOperation: "the aggregation state is null");
+    Ok(&mut *(state as *mut S))
+}
+
+/// # Safety
+/// `state` must be null or come from [`_aggregation_init`] of an aggregation with state `S`, and is
+/// consumed.
+unsafe fn take_state<S>(state: *mut c_void) -> PolarsResult<S> {
+    polars_ensure!(!state.is_null(), InvalidOperation: "the aggregation state is null");
+    Ok(*Box::from_raw(state as *mut S))
+}
+
+/// `kwargs` is called once the plugin is initialized.
+#[doc(hidden)]
+pub fn _aggregation_init<A: Aggregation>(
+    kwargs: impl FnOnce() -> PolarsResult<Arc<A::Kwargs>>,
+) -> *mut c_void {
+    ffi_call(|| {
+        _plugin_init()?;
+        Ok(Box::into_raw(Box::new(A::init(&kwargs()?)?)) as *mut c_void)
+    })
+    .unwrap_or(std::ptr::null_mut())
+}
+
+/// # Safety
+/// `state` must be null or come from [`_aggregation_init`] of the same aggregation, and `e` must
+/// point to `len` exported series, which are consumed.
+#[doc(hidden)]
+pub unsafe fn _aggregation_update<A: Aggregation>(
+    state: *mut c_void,
+    e: *mut SeriesExport,
+    len: usize,
+) -> bool {
+    ffi_call(|| {
+        let inputs = super::_import_inputs(e, len)?;
+        A::update(state_mut(state)?, &inputs)
+    })
+    .is_some()
+}
+
+/// # Safety
+/// Both states must be null or come from [`_aggregation_init`] of the same aggregation. `other` is
+/// consumed.
+#[doc(hidden)]
+pub unsafe fn _aggregation_combine<A: Aggregation>(state: *mut c_void, other: *mut c_void) -> bool {
+    ffi_call(|| {
+        let state = state_mut(state)?;
+        A::combine(state, take_state(other)?)
+    })
+    .is_some()
+}
+
+/// # Safety
+/// `state` must be null or come from [`_aggregation_init`] of the same aggregation, and is
+/// consumed.
+#[doc(hidden)]
+pub unsafe fn _aggregation_finalize<A: Aggregation>(
+    state: *mut c_void,
+    return_value: *mut SeriesExport,
+) -> bool {
+    ffi_call(|| {
+        let out = A::finalize(take_state(state)?)?;
+        *return_value = export_series(&out);
+        Ok(())
+    })
+    .is_some()
+}
+
+/// # Safety
+/// `state` must be null or come from [`_aggregation_init`] of the same aggregation, and is
+/// consumed.
+#[doc(hidden)]
+pub unsafe fn _aggregation_drop<A: Aggregation>(state: *mut c_void) {
+    ffi_call(|| take_state::<A::State>(state).map(drop));
+}
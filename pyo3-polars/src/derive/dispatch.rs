@@ -0,0 +1,103 @@
+//! Dtype dispatch of generic expression functions, as generated by
+//! `#[polars_expr(dispatch = [..])]`.
+//!
+//! ```rust,ignore
+//! #[polars_expr(output_type_func=haversine_output, dispatch=[Float32, Float64], cast_to=Float64)]
+//! fn haversine<T>(inputs: &[&ChunkedArray<T>]) -> PolarsResult<Series>
+//! where
+//!     T: PolarsFloatType,
+//!     T::Native: Float,
+//! {
+//!     naive_haversine(inputs[0], inputs[1], inputs[2], inputs[3]).map(|ca| ca.into_series())
+//! }
+//! ```
+//!
+//! The function is instantiated for every listed dtype. Without `cast_to`, all inputs must have the
+//! same listed dtype, which is dispatched to. With `cast_to`, numeric inputs are dispatched to their
+//! supertype, or to `cast_to` if the supertype isn't listed, and cast to it. Inputs are only cast
+//! upward: if `cast_to` is narrower than an input, e.g. `Float32` for a `Float64` input, the call
+//! fails instead of losing precision. Other dtypes are reported as unsupported.
+use polars::prelude::*;
+use polars_core::utils::try_get_supertype;
+
+fn unsupported(name: &str, dtype: &DataType, dtypes: &[DataType]) -> PolarsError {
+    polars_err!(
+        InvalidOperation: "{} doesn't support dtype {}, expected one of: {}",
+        name,
+        dtype,
+        dtypes.iter().map(|dtype| dtype.to_string()).collect::<Vec<_>>().join(", ")
+    )
+}
+
+/// The dtype to dispatch to and the inputs, cast to it where allowed.
+#[doc(hidden)]
+pub fn _dispatch_inputs(
+    name: &str,
+    inputs: &[Series],
+    dtypes: &[DataType],
+    cast_to: Option<DataType>,
+) -> PolarsResult<(DataType, Vec<Series>)> {
+    let is_numeric = |dtype: &DataType| dtype.is_integer() || dtype.is_float();
+    polars_ensure!(!inputs.is_empty(), InvalidOperation: "{} expects at least one input", name);
+
+    let Some(cast_to) = cast_to else {
+        let dtype = inputs[0].dtype();
+        if !dtypes.contains(dtype) {
+            return Err(unsupported(name, dtype, dtypes));
+        }
+        if let Some(s) = inputs.iter().find(|s| s.dtype() != dtype) {
+            polars_bail!(
+                SchemaMismatch: "{} was dispatched to dtype {}, but input {} has dtype {}",
+                name, dtype, s.name(), s.dtype()
+            );
+        }
+        return Ok((dtype.clone(), inputs.to_vec()));
+    };
+
+    let mut supertype = inputs[0].dtype().clone();
+    for s in inputs {
+        if !is_numeric(s.dtype()) {
+            return Err(unsupported(name, s.dtype(), dtypes));
+        }
+        supertype = try_get_supertype(&supertype, s.dtype())?;
+    }
+    let dtype = if dtypes.contains(&supertype) {
+        supertype
+    } else {
+        cast_to
+    };
+
+    let inputs = inputs
+        .iter()
+        .map(|s| {
+            let upward = try_get_supertype(s.dtype(), &dtype).is_ok_and(|st| st == dtype);
+            polars_ensure!(
+                upward,
+                SchemaMismatch: "{} was dispatched to dtype {}, casting input {} of dtype {} to it would narrow it",
+                name, dtype, s.name(), s.dtype()
+            );
+            s.cast(&dtype)
+        })
+        .collect::<PolarsResult<Vec<_>>>()?;
+    Ok((dtype, inputs))
+}
+
+/// Downcasts the inputs to the dtype they were dispatched to.
+#[doc(hidden)]
+pub fn _downcast_inputs<T: PolarsNumericType>(
+    inputs: &[Series],
+) -> PolarsResult<Vec<&ChunkedArray<T>>> {
+    inputs
+        .iter()
+        .map(|s| {
+            polars_ensure!(
+                s.dtype() == &T::get_dtype(),
+                SchemaMismatch: "cannot downcast input {} of dtype {} to {}",
+                s.name(), s.dtype(), T::get_dtype()
+            );
+            let s: &dyn SeriesTrait = s.as_ref();
+            let ca: &ChunkedArray<T> = s.as_ref();
+            Ok(ca)
+        })
+        .collect()
+}
@@ -0,0 +1,132 @@
+//! The kind of the last error of a plugin, so hosts can raise the matching exception instead of a
+//! `ComputeError`.
+//!
+//! `_polars_plugin_get_last_error_kind` returns the [`ErrorKind`] code of the last error and
+//! `_polars_plugin_get_last_error_context` the messages it was wrapped in with
+//! `PolarsError::context`, outermost first and separated by newlines.
+//! `_polars_plugin_get_last_error_message` still returns the full message.
+use std::sync::Arc;
+
+use polars::prelude::*;
+
+/// The kind of a plugin error, the variant of the `PolarsError` it was created from.
+///
+/// The codes are part of the FFI and don't change, new kinds get new codes.
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    /// No error was recorded on this thread.
+    None = 0,
+    /// `PolarsError::ColumnNotFound`
+    ColumnNotFound = 1,
+    /// `PolarsError::ComputeError`
+    ComputeError = 2,
+    /// `PolarsError::Duplicate`
+    Duplicate = 3,
+    /// `PolarsError::InvalidOperation`
+    InvalidOperation = 4,
+    /// `PolarsError::IO`
+    IO = 5,
+    /// `PolarsError::NoData`
+    NoData = 6,
+    /// `PolarsError::OutOfBounds`
+    OutOfBounds = 7,
+    /// `PolarsError::SchemaFieldNotFound`
+    SchemaFieldNotFound = 8,
+    /// `PolarsError::SchemaMismatch`
+    SchemaMismatch = 9,
+    /// `PolarsError::ShapeMismatch`
+    ShapeMismatch = 10,
+    /// `PolarsError::SQLInterface`
+    SQLInterface = 11,
+    /// `PolarsError::SQLSyntax`
+    SQLSyntax = 12,
+    /// `PolarsError::StringCacheMismatch`
+    StringCacheMismatch = 13,
+    /// `PolarsError::StructFieldNotFound`
+    StructFieldNotFound = 14,
+    /// The plugin panicked.
+    Panic = 15,
+}
+
+const KINDS: [ErrorKind; 16] = [
+    ErrorKind::None,
+    ErrorKind::ColumnNotFound,
+    ErrorKind::ComputeError,
+    ErrorKind::Duplicate,
+    ErrorKind::InvalidOperation,
+    ErrorKind::IO,
+    ErrorKind::NoData,
+    ErrorKind::OutOfBounds,
+    ErrorKind::SchemaFieldNotFound,
+    ErrorKind::SchemaMismatch,
+    ErrorKind::ShapeMismatch,
+    ErrorKind::SQLInterface,
+    ErrorKind::SQLSyntax,
+    ErrorKind::StringCacheMismatch,
+    ErrorKind::StructFieldNotFound,
+    ErrorKind::Panic,
+];
+
+impl ErrorKind {
+    /// The kind of `err`, looking through its context.
+    pub fn of(err: &PolarsError) -> Self {
+        match err {
+            PolarsError::ColumnNotFound(_) => Self::ColumnNotFound,
+            PolarsError::ComputeError(_) => Self::ComputeError,
+            PolarsError::Duplicate(_) => Self::Duplicate,
+            PolarsError::InvalidOperation(_) => Self::InvalidOperation,
+            PolarsError::IO { .. } => Self::IO,
+            PolarsError::NoData(_) => Self::NoData,
+            PolarsError::OutOfBounds(_) => Self::OutOfBounds,
+            PolarsError::SchemaFieldNotFound(_) => Self::SchemaFieldNotFound,
+            PolarsError::SchemaMismatch(_) => Self::SchemaMismatch,
+            PolarsError::ShapeMismatch(_) => Self::ShapeMismatch,
+            PolarsError::SQLInterface(_) => Self::SQLInterface,
+            PolarsError::SQLSyntax(_) => Self::SQLSyntax,
+            PolarsError::StringCacheMismatch(_) => Self::StringCacheMismatch,
+            PolarsError::StructFieldNotFound(_) => Self::StructFieldNotFound,
+            PolarsError::Context { error, .. } => Self::of(error),
+        }
+    }
+
+    /// The kind of an FFI code, `None` for codes of newer versions.
+    pub fn from_code(code: u32) -> Option<Self> {
+        KINDS.get(code as usize).copied()
+    }
+
+    /// The FFI code of the kind.
+    pub fn code(self) -> u32 {
+        self as u32
+    }
+
+    /// A `PolarsError` of this kind. Errors that aren't a `PolarsError` variant become a
+    /// `ComputeError`.
+    pub fn into_error(self, msg: impl Into<ErrString>) -> PolarsError {
+        let msg = msg.into();
+        match self {
+            Self::ColumnNotFound => PolarsError::ColumnNotFound(msg),
+            Self::Duplicate => PolarsError::Duplicate(msg),
+            Self::InvalidOperation => PolarsError::InvalidOperation(msg),
+            Self::IO => PolarsError::IO {
+                error: Arc::new(std::io::Error::other(msg.to_string())),
+                msg: None,
+            },
+            Self::NoData => PolarsError::NoData(msg),
+            Self::OutOfBounds => PolarsError::OutOfBounds(msg),
+            Self::SchemaFieldNotFound => PolarsError::SchemaFieldNotFound(msg),
+            Self::SchemaMismatch => PolarsError::SchemaMismatch(msg),
+            Self::ShapeMismatch => PolarsError::ShapeMismatch(msg),
+            Self::SQLInterface => PolarsError::SQLInterface(msg),
+            Self::SQLSyntax => PolarsError::SQLSyntax(msg),
+            Self::StringCacheMismatch => PolarsError::StringCacheMismatch(msg),
+            Self::StructFieldNotFound => PolarsError::StructFieldNotFound(msg),
+            Self::None | Self::ComputeError | Self::Panic => PolarsError::ComputeError(msg),
+        }
+    }
+}
+
+/// The context messages `err` is wrapped in, outermost first.
+pub fn error_context(err: &PolarsError) -> Vec<String> {
+    crate::error::context_chain(err).1
+}
@@ -0,0 +1,62 @@
+//! Import the inputs of plugin calls, rejecting null pointers and released exports before they
+//! are read.
+use polars::prelude::*;
+use polars_core::export::arrow::ffi::{import_field_from_c, ArrowSchema};
+use polars_ffi::version_0::{import_series, SeriesExport};
+
+/// Imports the input series of an expression call.
+///
+/// # Safety
+/// `e` must be null or point to `len` series exports, which are consumed.
+#[doc(hidden)]
+pub unsafe fn _import_inputs(e: *mut SeriesExport, len: usize) -> PolarsResult<Vec<Series>> {
+    if len == 0 {
+        return Ok(vec![]);
+    }
+    polars_ensure!(
+        !e.is_null(),
+        ComputeError: "could not import the inputs: got a null pointer for {} series", len
+    );
+    // Read all exports first, so the valid ones are released if one of them can't be imported.
+    let exports = (0..len)
+        .map(|i| std::ptr::read(e.add(i)))
+        .collect::<Vec<_>>();
+    if let Some(i) = exports.iter().position(|export| export.is_null()) {
+        polars_bail!(ComputeError: "could not import input {}: the series export is released", i);
+    }
+    exports
+        .into_iter()
+        .enumerate()
+        .map(|(i, export)| {
+            import_series(export).map_err(|err| err.context(format!("importing input {i}").into()))
+        })
+        .collect()
+}
+
+/// Imports the input fields of a field call.
+///
+/// # Safety
+/// `field` must be null or point to `len` Arrow schemas, which are borrowed.
+#[doc(hidden)]
+pub unsafe fn _import_fields(field: *const ArrowSchema, len: usize) -> PolarsResult<Vec<Field>> {
+    if len == 0 {
+        return Ok(vec![]);
+    }
+    polars_ensure!(
+        !field.is_null(),
+        ComputeError: "could not import the input fields: got a null pointer for {} fields", len
+    );
+    std::slice::from_raw_parts(field, len)
+        .iter()
+        .enumerate()
+        .map(|(i, schema)| {
+            polars_ensure!(
+                !schema.is_null(),
+                ComputeError: "could not import input field {}: the ArrowSchema is released", i
+            );
+            let field = import_field_from_c(schema)
+                .map_err(|err| err.context(format!("importing input field {i}").into()))?;
+            Ok(Field::from(&field))
+        })
+        .collect()
+}
@@ -0,0 +1,318 @@
+//! Serialized kwargs.
+//!
+//! Python pickles the kwargs passed to `register_plugin_function`. Other hosts can't easily produce
+//! pickles, so kwargs may also be wrapped in an envelope that names their encoding: the
+//! [`KWARGS_MAGIC`] bytes, a [`KwargsEncoding`] tag byte and the payload. Kwargs without the
+//! envelope are read as pickle. JSON, CBOR and MessagePack are opt-in, with the `kwargs-json`,
+//! `kwargs-cbor` and `kwargs-msgpack` features.
+//!
+//! Errors name the kwarg that failed to parse. Kwargs types that derive [`KwargsSchema`] also get
+//! the expected Rust type and description of the kwarg in the error.
+use std::fmt::Write;
+use std::marker::PhantomData;
+
+use polars::prelude::*;
+use polars_core::error::to_compute_err;
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_path_to_error::{Segment, Track};
+
+/// Starts kwargs wrapped in the envelope. A pickle never starts with these bytes.
+pub const KWARGS_MAGIC: &[u8; 4] = b"PLKW";
+
+/// The encoding of serialized kwargs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum KwargsEncoding {
+    /// The Python pickle protocol, as used by `register_plugin_function`.
+    Pickle,
+    /// JSON.
+    Json,
+    /// CBOR.
+    Cbor,
+    /// MessagePack.
+    MessagePack,
+}
+
+impl KwargsEncoding {
+    /// The tag byte of this encoding in the envelope.
+    pub fn tag(self) -> u8 {
+        match self {
+            KwargsEncoding::Pickle => 0,
+            KwargsEncoding::Json => 1,
+            KwargsEncoding::Cbor => 2,
+            KwargsEncoding::MessagePack => 3,
+        }
+    }
+
+    /// The feature of pyo3-polars that reads and writes this encoding.
+    fn feature(self) -> &'static str {
+        match self {
+            KwargsEncoding::Pickle => "derive",
+            KwargsEncoding::Json => "kwargs-json",
+            KwargsEncoding::Cbor => "kwargs-cbor",
+            KwargsEncoding::MessagePack => "kwargs-msgpack",
+        }
+    }
+
+    /// The encoding with the given tag byte.
+    pub fn from_tag(tag: u8) -> Option<Self> {
+        match tag {
+            0 => Some(KwargsEncoding::Pickle),
+            1 => Some(KwargsEncoding::Json),
+            2 => Some(KwargsEncoding::Cbor),
+            3 => Some(KwargsEncoding::MessagePack),
+            _ => None,
+        }
+    }
+}
+
+fn encoding_disabled(encoding: KwargsEncoding) -> PolarsError {
+    polars_err!(
+        ComputeError: "{:?} kwargs need the `{}` feature of pyo3-polars",
+        encoding, encoding.feature()
+    )
+}
+
+/// Splits serialized kwargs in their encoding and payload.
+pub fn split_kwargs(kwargs: &[u8]) -> PolarsResult<(KwargsEncoding, &[u8])> {
+    match kwargs.strip_prefix(KWARGS_MAGIC) {
+        None => Ok((KwargsEncoding::Pickle, kwargs)),
+        Some([tag, payload @ ..]) => match KwargsEncoding::from_tag(*tag) {
+            Some(encoding) => Ok((encoding, payload)),
+            None => polars_bail!(ComputeError: "unknown kwargs encoding tag: {}", tag),
+        },
+        Some([]) => polars_bail!(ComputeError: "kwargs envelope is missing the encoding tag"),
+    }
+}
+
+/// Serializes kwargs, so that they can be passed to a plugin by a host other than Python.
+///
+/// Pickle is written without the envelope, so it can also be read by plugins built before
+/// other encodings were supported.
+pub fn serialize_kwargs<T: Serialize>(
+    kwargs: &T,
+    encoding: KwargsEncoding,
+) -> PolarsResult<Vec<u8>> {
+    if encoding == KwargsEncoding::Pickle {
+        return serde_pickle::to_vec(kwargs, Default::default()).map_err(to_compute_err);
+    }
+
+    let mut out = KWARGS_MAGIC.to_vec();
+    out.push(encoding.tag());
+    match encoding {
+        KwargsEncoding::Pickle => unreachable!(),
+        #[cfg(feature = "kwargs-json")]
+        KwargsEncoding::Json => serde_json::to_writer(&mut out, kwargs).map_err(to_compute_err)?,
+        #[cfg(feature = "kwargs-cbor")]
+        KwargsEncoding::Cbor => ciborium::into_writer(kwargs, &mut out).map_err(to_compute_err)?,
+        #[cfg(feature = "kwargs-msgpack")]
+        KwargsEncoding::MessagePack => {
+            rmp_serde::encode::write_named(&mut out, kwargs).map_err(to_compute_err)?
+        }
+        #[allow(unreachable_patterns)]
+        encoding => return Err(encoding_disabled(encoding)),
+    }
+    Ok(out)
+}
+
+/// Describes a kwarg of a [`KwargsSchema`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct KwargField {
+    /// The name of the kwarg, after `#[serde(rename)]`.
+    pub name: &'static str,
+    /// The Rust type the kwarg is deserialized into.
+    pub rust_type: &'static str,
+    /// The doc comment of the field.
+    pub description: &'static str,
+    /// Whether the kwarg may be omitted, because of `#[serde(default)]` or an `Option` type.
+    pub has_default: bool,
+}
+
+/// The kwargs a plugin function expects, derived with `#[derive(KwargsSchema)]` next to
+/// `Deserialize`:
+///
+/// ```rust,ignore
+/// #[derive(Deserialize, KwargsSchema)]
+/// struct PigLatinKwargs {
+///     /// Capitalize the output.
+///     #[serde(default)]
+///     capitalize: bool,
+/// }
+/// ```
+///
+/// The expression and the output type function of a `#[polars_expr]` taking such kwargs report
+/// which kwarg is missing or has the wrong type, together with its expected Rust type.
+pub trait KwargsSchema {
+    /// The fields of the kwargs struct, in declaration order.
+    fn kwargs_fields() -> &'static [KwargField];
+}
+
+/// Looks up the kwarg that failed, if the schema is known.
+#[doc(hidden)]
+pub trait _SchemaLookup {
+    fn fields() -> &'static [KwargField];
+}
+
+#[doc(hidden)]
+pub struct _NoSchema;
+
+impl _SchemaLookup for _NoSchema {
+    fn fields() -> &'static [KwargField] {
+        &[]
+    }
+}
+
+#[doc(hidden)]
+pub struct _WithSchema<T>(PhantomData<T>);
+
+impl<T: KwargsSchema> _SchemaLookup for _WithSchema<T> {
+    fn fields() -> &'static [KwargField] {
+        T::kwargs_fields()
+    }
+}
+
+/// Deserializes `T` and names the kwarg that failed in the error.
+struct Tracked<T, S>(T, PhantomData<S>);
+
+impl<'de, T: Deserialize<'de>, S: _SchemaLookup> Deserialize<'de> for Tracked<T, S> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut track = Track::new();
+        let deserializer = serde_path_to_error::Deserializer::new(deserializer, &mut track);
+        match T::deserialize(deserializer) {
+            Ok(value) => Ok(Tracked(value, PhantomData)),
+            Err(err) => Err(D::Error::custom(kwargs_error::<S>(
+                &track.path(),
+                &err.to_string(),
+            ))),
+        }
+    }
+}
+
+fn describe(field: &KwargField) -> String {
+    let mut out = format!("`{}`", field.rust_type);
+    if field.has_default {
+        out.push_str(", optional");
+    }
+    if !field.description.is_empty() {
+        write!(out, ", {}", field.description).unwrap();
+    }
+    out
+}
+
+fn kwargs_error<S: _SchemaLookup>(path: &serde_path_to_error::Path, msg: &str) -> String {
+    let fields = S::fields();
+    let mut out = match path.iter().next() {
+        // Missing fields are reported by the struct itself, so they have an empty path.
+        None => match fields
+            .iter()
+            .find(|field| msg.contains(&format!("missing field `{}`", field.name)))
+        {
+            Some(field) => format!("missing kwarg `{}` ({})", field.name, describe(field)),
+            None => msg.to_string(),
+        },
+        Some(Segment::Map { key }) => match fields.iter().find(|field| field.name == key) {
+            Some(field) => format!("invalid kwarg `{path}` ({}): {msg}", describe(field)),
+            None => format!("invalid kwarg `{path}`: {msg}"),
+        },
+        Some(_) => format!("invalid kwarg `{path}`: {msg}"),
+    };
+
+    if !fields.is_empty() {
+        out.push_str("\n\nexpected kwargs:");
+        for field in fields {
+            write!(out, "\n  {}: {}", field.name, describe(field)).unwrap();
+        }
+    }
+    out
+}
+
+fn parse_tracked<'a, T, S>(kwargs: &'a [u8]) -> PolarsResult<T>
+where
+    T: Deserialize<'a>,
+    S: _SchemaLookup,
+{
+    let (encoding, payload) = split_kwargs(kwargs)?;
+    let tracked: Tracked<T, S> = match encoding {
+        KwargsEncoding::Pickle => {
+            serde_pickle::from_slice(payload, Default::default()).map_err(to_compute_err)?
+        }
+        #[cfg(feature = "kwargs-json")]
+        KwargsEncoding::Json => serde_json::from_slice(payload).map_err(to_compute_err)?,
+        #[cfg(feature = "kwargs-cbor")]
+        KwargsEncoding::Cbor => {
+            // `ciborium::from_reader` can't borrow from the input, a `Value` can be deserialized
+            // into any type.
+            let value: ciborium::Value = ciborium::from_reader(payload).map_err(to_compute_err)?;
+            value.deserialized().map_err(to_compute_err)?
+        }
+        #[cfg(feature = "kwargs-msgpack")]
+        KwargsEncoding::MessagePack => rmp_serde::from_slice(payload).map_err(to_compute_err)?,
+        #[allow(unreachable_patterns)]
+        encoding => return Err(encoding_disabled(encoding)),
+    };
+    Ok(tracked.0)
+}
+
+/// deserializes a kwargs object, pickled or wrapped in the envelope
+pub fn _parse_kwargs<'a, T>(kwargs: &'a [u8]) -> PolarsResult<T>
+where
+    T: Deserialize<'a>,
+{
+    parse_tracked::<T, _NoSchema>(kwargs)
+}
+
+/// deserializes a kwargs object, and describes the expected kwargs on failure
+pub fn _parse_kwargs_with_schema<'a, T>(kwargs: &'a [u8]) -> PolarsResult<T>
+where
+    T: Deserialize<'a> + KwargsSchema,
+{
+    parse_tracked::<T, _WithSchema<T>>(kwargs)
+}
+
+/// Picks [`_parse_kwargs_with_schema`] for kwargs types implementing [`KwargsSchema`] and
+/// [`_parse_kwargs`] for others, with autoref specialization:
+///
+/// ```rust,ignore
+/// use pyo3_polars::derive::{_ParseKwargs, _ParseKwargsWithSchema};
+/// let kwargs = (&pyo3_polars::derive::_kwargs_parser::<MyKwargs>()).parse_kwargs(bytes)?;
+/// ```
+#[doc(hidden)]
+pub struct _KwargsParser<K>(PhantomData<K>);
+
+/// The parser of kwargs of type `K`.
+#[doc(hidden)]
+pub fn _kwargs_parser<K>() -> _KwargsParser<K> {
+    _KwargsParser(PhantomData)
+}
+
+#[doc(hidden)]
+pub trait _ParseKwargsWithSchema<K> {
+    fn parse_kwargs<'a>(&self, kwargs: &'a [u8]) -> PolarsResult<K>
+    where
+        K: Deserialize<'a>;
+}
+
+impl<K: KwargsSchema> _ParseKwargsWithSchema<K> for _KwargsParser<K> {
+    fn parse_kwargs<'a>(&self, kwargs: &'a [u8]) -> PolarsResult<K>
+    where
+        K: Deserialize<'a>,
+    {
+        _parse_kwargs_with_schema(kwargs)
+    }
+}
+
+#[doc(hidden)]
+pub trait _ParseKwargs<K> {
+    fn parse_kwargs<'a>(&self, kwargs: &'a [u8]) -> PolarsResult<K>
+    where
+        K: Deserialize<'a>;
+}
+
+impl<K> _ParseKwargs<K> for &_KwargsParser<K> {
+    fn parse_kwargs<'a>(&self, kwargs: &'a [u8]) -> PolarsResult<K>
+    where
+        K: Deserialize<'a>,
+    {
+        _parse_kwargs(kwargs)
+    }
+}
@@ -0,0 +1,120 @@
+//! One-time plugin initialization and state cached by kwargs.
+//!
+//! A function marked `#[polars_plugin_init]` runs once per process, before the first expression,
+//! output type function or aggregation of the plugin is called:
+//!
+//! ```rust,ignore
+//! #[polars_plugin_init]
+//! fn init() -> PolarsResult<()> {
+//!     load_dictionary()
+//! }
+//! ```
+//!
+//! If it fails, the call fails with its error and the next call runs the `#[polars_plugin_init]`
+//! functions again, including those that succeeded, so a transient failure, e.g. of a download,
+//! doesn't break the plugin for the rest of the process.
+//!
+//! State that is expensive to build from the kwargs, e.g. a compiled regex, implements
+//! [`PluginState`] and is taken as `state: &MyState` instead of `kwargs`:
+//!
+//! ```rust,ignore
+//! struct Matcher(Regex);
+//!
+//! impl PluginState for Matcher {
+//!     type Kwargs = MatcherKwargs;
+//!
+//!     fn build(kwargs: MatcherKwargs) -> PolarsResult<Self> {
+//!         Regex::new(&kwargs.pattern).map(Matcher).map_err(to_compute_err)
+//!     }
+//! }
+//!
+//! #[polars_expr(output_type=Boolean)]
+//! fn is_match(inputs: &[Series], state: &Matcher) -> PolarsResult<Series> { .. }
+//! ```
+//!
+//! The state is cached by the serialized kwargs, so it is built once for all chunks and groups
+//! Polars calls the expression with.
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use polars::prelude::*;
+use serde::de::DeserializeOwned;
+
+use super::ensure_start_up;
+
+/// Above this many states, the cache is cleared before inserting a new one.
+const STATE_CACHE_CAPACITY: usize = 256;
+
+type StateCache = HashMap<(TypeId, Vec<u8>), Arc<dyn Any + Send + Sync>>;
+
+/// A `#[polars_plugin_init]` function, submitted with `inventory` when the library is loaded.
+/// Submitting doesn't allocate or lock, so it is sound under the loader lock.
+#[doc(hidden)]
+pub struct _PluginInitHook(pub fn() -> PolarsResult<()>);
+
+inventory::collect!(_PluginInitHook);
+
+static PLUGIN_INITIALIZED: AtomicBool = AtomicBool::new(false);
+/// Held while the `#[polars_plugin_init]` functions run, so they run on one thread.
+static PLUGIN_INIT: Mutex<()> = Mutex::new(());
+static STATE_CACHE: OnceLock<Mutex<StateCache>> = OnceLock::new();
+
+/// Runs the start up and, until they succeed, the `#[polars_plugin_init]` functions.
+#[doc(hidden)]
+pub fn _plugin_init() -> PolarsResult<()> {
+    ensure_start_up();
+    if PLUGIN_INITIALIZED.load(Ordering::Acquire) {
+        return Ok(());
+    }
+    let _guard = PLUGIN_INIT.lock().unwrap_or_else(|err| err.into_inner());
+    if PLUGIN_INITIALIZED.load(Ordering::Acquire) {
+        return Ok(());
+    }
+    inventory::iter::<_PluginInitHook>
+        .into_iter()
+        .try_for_each(|hook| (hook.0)())
+        .map_err(|err| polars_err!(ComputeError: "plugin initialization failed: {}", err))?;
+    PLUGIN_INITIALIZED.store(true, Ordering::Release);
+    Ok(())
+}
+
+/// State built from the kwargs of an expression and reused for all calls with the same kwargs.
+pub trait PluginState: Sized + Send + Sync + 'static {
+    /// The kwargs the state is built from.
+    type Kwargs: DeserializeOwned;
+
+    /// Build the state. This is called once per distinct kwargs, as long as the state is cached.
+    fn build(kwargs: Self::Kwargs) -> PolarsResult<Self>;
+}
+
+/// Returns the cached state of these kwargs, building it with the parsed kwargs if needed.
+#[doc(hidden)]
+pub fn _cached_state<S: PluginState>(
+    kwargs: &[u8],
+    parse: impl FnOnce(&[u8]) -> PolarsResult<S::Kwargs>,
+) -> PolarsResult<Arc<S>> {
+    let cache = STATE_CACHE.get_or_init(Default::default);
+    let key = (TypeId::of::<S>(), kwargs.to_vec());
+    if let Some(state) = cache.lock().unwrap().get(&key) {
+        return Ok(state.clone().downcast::<S>().unwrap());
+    }
+
+    // Built without holding the lock, concurrent calls may build the same state and the first
+    // one is kept.
+    let state: Arc<dyn Any + Send + Sync> = Arc::new(S::build(parse(kwargs)?)?);
+    let mut cache = cache.lock().unwrap();
+    if cache.len() >= STATE_CACHE_CAPACITY {
+        cache.clear();
+    }
+    let state = cache.entry(key).or_insert(state).clone();
+    Ok(state.downcast::<S>().unwrap())
+}
+
+/// Drops all cached [`PluginState`]s.
+pub fn clear_plugin_state() {
+    if let Some(cache) = STATE_CACHE.get() {
+        cache.lock().unwrap().clear();
+    }
+}
@@ -0,0 +1,182 @@
+//! Output type functions, as given to `#[polars_expr(output_type_func = ..)]`.
+//!
+//! Like the expression function, an output type function takes the input fields and optionally a
+//! `context` and `kwargs`, in this order:
+//!
+//! ```rust,ignore
+//! fn output(input_fields: &[Field]) -> PolarsResult<Field>;
+//! fn output(input_fields: &[Field], kwargs: MyKwargs) -> PolarsResult<Field>;
+//! fn output(input_fields: &[Field], context: CallerContext) -> PolarsResult<Field>;
+//! fn output(input_fields: &[Field], context: CallerContext, kwargs: MyKwargs) -> PolarsResult<Field>;
+//! ```
+//!
+//! Polars' field function has no context parameter, so `_polars_plugin_field_<name>` passes
+//! `CallerContext::default()`. Hosts that know the context of the call, like the
+//! [`loader`](crate::loader), pass it to `_polars_plugin_context_field_<name>` instead, which takes
+//! a `*mut CallerContext` after the kwargs.
+//!
+//! The function returns anything implementing [`IntoOutputField`]: a [`Field`], whose name
+//! overrides the output name, or a [`DataType`], which is named after the first input.
+use std::marker::PhantomData;
+
+use polars::prelude::*;
+use polars_plan::dsl::FieldsMapper;
+use serde::de::DeserializeOwned;
+
+use super::{CallerContext, _KwargsParser, _kwargs_parser};
+
+/// The output of an output type function.
+pub trait IntoOutputField {
+    /// The output field, given the input fields.
+    fn into_output_field(self, input_fields: &[Field]) -> PolarsResult<Field>;
+}
+
+impl IntoOutputField for Field {
+    fn into_output_field(self, _input_fields: &[Field]) -> PolarsResult<Field> {
+        Ok(self)
+    }
+}
+
+impl IntoOutputField for DataType {
+    fn into_output_field(self, input_fields: &[Field]) -> PolarsResult<Field> {
+        _output_field_with_dtype(input_fields, self)
+    }
+}
+
+/// The output field of a static `output_type`, named after the first input.
+#[doc(hidden)]
+pub fn _output_field_with_dtype(input_fields: &[Field], dtype: DataType) -> PolarsResult<Field> {
+    polars_ensure!(
+        !input_fields.is_empty(),
+        InvalidOperation: "expected at least one input, the output is named after the first"
+    );
+    FieldsMapper::new(input_fields).with_dtype(dtype)
+}
+
+/// `output(input_fields)`
+#[doc(hidden)]
+pub struct _Fields;
+
+/// `output(input_fields, kwargs)`
+#[doc(hidden)]
+pub struct _FieldsKwargs<K>(PhantomData<K>);
+
+/// `output(input_fields, context)`
+#[doc(hidden)]
+pub struct _FieldsContext;
+
+/// `output(input_fields, context, kwargs)`
+#[doc(hidden)]
+pub struct _FieldsContextKwargs<K>(PhantomData<K>);
+
+/// Implemented for the supported signatures of output type functions, `Args` is inferred from the
+/// function.
+#[doc(hidden)]
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` is not an output type function",
+    note = "output type functions take `input_fields: &[Field]` and optionally `context: CallerContext` and `kwargs: K`, \
+            in this order, and return `PolarsResult` of a `Field` or `DataType`"
+)]
+pub trait _OutputTypeFunc<Args> {
+    type Kwargs;
+
+    fn call_output_type(
+        &self,
+        input_fields: &[Field],
+        context: CallerContext,
+        kwargs: impl FnOnce() -> PolarsResult<Self::Kwargs>,
+    ) -> PolarsResult<Field>;
+}
+
+impl<F, R> _OutputTypeFunc<_Fields> for F
+where
+    F: Fn(&[Field]) -> PolarsResult<R>,
+    R: IntoOutputField,
+{
+    type Kwargs = ();
+
+    fn call_output_type(
+        &self,
+        input_fields: &[Field],
+        _context: CallerContext,
+        _kwargs: impl FnOnce() -> PolarsResult<()>,
+    ) -> PolarsResult<Field> {
+        self(input_fields)?.into_output_field(input_fields)
+    }
+}
+
+impl<F, R, K> _OutputTypeFunc<_FieldsKwargs<K>> for F
+where
+    F: Fn(&[Field], K) -> PolarsResult<R>,
+    R: IntoOutputField,
+    K: DeserializeOwned,
+{
+    type Kwargs = K;
+
+    fn call_output_type(
+        &self,
+        input_fields: &[Field],
+        _context: CallerContext,
+        kwargs: impl FnOnce() -> PolarsResult<K>,
+    ) -> PolarsResult<Field> {
+        self(input_fields, kwargs()?)?.into_output_field(input_fields)
+    }
+}
+
+impl<F, R> _OutputTypeFunc<_FieldsContext> for F
+where
+    F: Fn(&[Field], CallerContext) -> PolarsResult<R>,
+    R: IntoOutputField,
+{
+    type Kwargs = ();
+
+    fn call_output_type(
+        &self,
+        input_fields: &[Field],
+        context: CallerContext,
+        _kwargs: impl FnOnce() -> PolarsResult<()>,
+    ) -> PolarsResult<Field> {
+        self(input_fields, context)?.into_output_field(input_fields)
+    }
+}
+
+impl<F, R, K> _OutputTypeFunc<_FieldsContextKwargs<K>> for F
+where
+    F: Fn(&[Field], CallerContext, K) -> PolarsResult<R>,
+    R: IntoOutputField,
+    K: DeserializeOwned,
+{
+    type Kwargs = K;
+
+    fn call_output_type(
+        &self,
+        input_fields: &[Field],
+        context: CallerContext,
+        kwargs: impl FnOnce() -> PolarsResult<K>,
+    ) -> PolarsResult<Field> {
+        self(input_fields, context, kwargs()?)?.into_output_field(input_fields)
+    }
+}
+
+/// The parser of the kwargs of an output type function, see [`_KwargsParser`].
+#[doc(hidden)]
+pub fn _output_type_kwargs_parser<F, A>(_f: &F) -> _KwargsParser<F::Kwargs>
+where
+    F: _OutputTypeFunc<A>,
+{
+    _kwargs_parser()
+}
+
+/// Calls an output type function. `kwargs` is only called if the function takes kwargs.
+#[doc(hidden)]
+pub fn _call_output_type<F, A>(
+    f: &F,
+    input_fields: &[Field],
+    context: CallerContext,
+    kwargs: impl FnOnce() -> PolarsResult<F::Kwargs>,
+) -> PolarsResult<Field>
+where
+    F: _OutputTypeFunc<A>,
+{
+    f.call_output_type(input_fields, context, kwargs)
+}
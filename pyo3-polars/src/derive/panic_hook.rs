@@ -0,0 +1,95 @@
+//! The panic hook of plugins.
+//!
+//! On start up, plugins install a panic hook that chains the hook installed before it. Panics
+//! inside plugin calls are recorded with their location and backtrace, so they can be returned as
+//! the last error, and are only printed by the previous hook if `POLARS_VERBOSE` is "1". Panics
+//! elsewhere in the process are passed to the previous hook untouched.
+//!
+//! Plugins that manage panics themselves opt out with [`disable_panic_hook`], e.g. from a
+//! `#[polars_plugin_init]` function, and [`set_plugin_panic_hook`] replaces how panics inside
+//! plugin calls are reported.
+use std::cell::Cell;
+use std::panic::PanicHookInfo;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+
+use super::{panic_message, LAST_PANIC};
+
+type PluginPanicHook = Box<dyn Fn(&PanicHookInfo) + Send + Sync>;
+
+static DISABLED: AtomicBool = AtomicBool::new(false);
+static PLUGIN_PANIC_HOOK: RwLock<Option<PluginPanicHook>> = RwLock::new(None);
+
+thread_local! {
+    /// The number of plugin calls running on this thread, a plugin may call another one.
+    static CALL_DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Marks the current thread as running a plugin call until it is dropped.
+#[doc(hidden)]
+pub struct _PluginCall(());
+
+impl _PluginCall {
+    #[doc(hidden)]
+    pub fn enter() -> Self {
+        CALL_DEPTH.with(|depth| depth.set(depth.get() + 1));
+        Self(())
+    }
+}
+
+impl Drop for _PluginCall {
+    fn drop(&mut self) {
+        let depth = CALL_DEPTH.with(|depth| {
+            depth.set(depth.get() - 1);
+            depth.get()
+        });
+        if depth == 0 {
+            crate::warnings::flush_call_warnings();
+        }
+    }
+}
+
+pub(crate) fn in_plugin_call() -> bool {
+    CALL_DEPTH.with(|depth| depth.get() > 0)
+}
+
+/// Leaves all panics to the previous panic hook.
+///
+/// Panic messages are then taken from the panic payload, without location and backtrace.
+pub fn disable_panic_hook() {
+    DISABLED.store(true, Ordering::Relaxed);
+}
+
+/// Calls `hook` for panics inside plugin calls instead of the previous panic hook.
+///
+/// The panic is still recorded as the last error.
+pub fn set_plugin_panic_hook(hook: impl Fn(&PanicHookInfo) + Send + Sync + 'static) {
+    *PLUGIN_PANIC_HOOK
+        .write()
+        .unwrap_or_else(|err| err.into_inner()) = Some(Box::new(hook));
+}
+
+/// Installs the panic hook, chaining the current one.
+pub(super) fn install() {
+    if DISABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    let previous_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if DISABLED.load(Ordering::Relaxed) || !in_plugin_call() {
+            return previous_hook(info);
+        }
+
+        let msg = panic_message(info);
+        LAST_PANIC.with(|prev| *prev.borrow_mut() = Some(msg));
+
+        let plugin_hook = PLUGIN_PANIC_HOOK
+            .read()
+            .unwrap_or_else(|err| err.into_inner());
+        if let Some(hook) = plugin_hook.as_ref() {
+            hook(info)
+        } else if std::env::var("POLARS_VERBOSE").as_deref().unwrap_or("") == "1" {
+            previous_hook(info)
+        }
+    }));
+}
@@ -0,0 +1,151 @@
+//! Scalar arguments of expression functions, see [`ScalarArg`].
+use polars::prelude::*;
+
+/// A Rust value that can be passed as a literal expression, e.g. `pl.lit(0.5)`.
+///
+/// Parameters of a `#[polars_expr]` function between the inputs and `context`/`kwargs` are
+/// extracted from the last inputs, in order, which must be of length 1:
+///
+/// ```rust,ignore
+/// #[polars_expr(output_type=Boolean, args=[expr, threshold])]
+/// fn above(inputs: &[Series], threshold: f64) -> PolarsResult<Series> {
+///     Ok(inputs[0].f64()?.gt(threshold).into_series())
+/// }
+/// ```
+///
+/// Numbers are cast to the type of the parameter, floats aren't accepted for integers. Other
+/// dtypes, e.g. strings, aren't cast to numbers. Nulls are only accepted by `Option` parameters.
+pub trait ScalarArg<'a>: Sized {
+    /// The value of a Series of length 1, `None` if it is null.
+    fn from_scalar(s: &'a Series) -> PolarsResult<Option<Self>>;
+
+    /// The value of a null, if accepted.
+    fn from_null() -> Option<Self> {
+        None
+    }
+}
+
+// Integers are read as 64 bit, as the narrower dtypes are behind features of polars.
+macro_rules! impl_scalar_arg_integer {
+    ($($native:ty => $dtype:ident, $getter:ident;)*) => {
+        $(
+            impl ScalarArg<'_> for $native {
+                #[allow(clippy::useless_conversion)]
+                fn from_scalar(s: &Series) -> PolarsResult<Option<Self>> {
+                    polars_ensure!(
+                        s.dtype().is_integer() || s.dtype().is_null(),
+                        SchemaMismatch: "expected an integer, got {}", s.dtype()
+                    );
+                    let s = s.strict_cast(&DataType::$dtype)?;
+                    s.$getter()?
+                        .get(0)
+                        .map(|value| {
+                            <$native>::try_from(value).map_err(|_| {
+                                polars_err!(ComputeError: "{} is out of range for `{}`", value, stringify!($native))
+                            })
+                        })
+                        .transpose()
+                }
+            }
+        )*
+    };
+}
+
+impl_scalar_arg_integer!(
+    i8 => Int64, i64;
+    i16 => Int64, i64;
+    i32 => Int64, i64;
+    i64 => Int64, i64;
+    u8 => UInt64, u64;
+    u16 => UInt64, u64;
+    u32 => UInt64, u64;
+    u64 => UInt64, u64;
+    usize => UInt64, u64;
+);
+
+fn ensure_number(s: &Series) -> PolarsResult<()> {
+    polars_ensure!(
+        s.dtype().is_integer() || s.dtype().is_float() || s.dtype().is_null(),
+        SchemaMismatch: "expected a number, got {}", s.dtype()
+    );
+    Ok(())
+}
+
+impl ScalarArg<'_> for f32 {
+    fn from_scalar(s: &Series) -> PolarsResult<Option<Self>> {
+        ensure_number(s)?;
+        Ok(s.strict_cast(&DataType::Float32)?.f32()?.get(0))
+    }
+}
+
+impl ScalarArg<'_> for f64 {
+    fn from_scalar(s: &Series) -> PolarsResult<Option<Self>> {
+        ensure_number(s)?;
+        Ok(s.strict_cast(&DataType::Float64)?.f64()?.get(0))
+    }
+}
+
+impl ScalarArg<'_> for bool {
+    fn from_scalar(s: &Series) -> PolarsResult<Option<Self>> {
+        Ok(s.bool()?.get(0))
+    }
+}
+
+impl<'a> ScalarArg<'a> for &'a str {
+    fn from_scalar(s: &'a Series) -> PolarsResult<Option<Self>> {
+        Ok(s.str()?.get(0))
+    }
+}
+
+impl ScalarArg<'_> for String {
+    fn from_scalar(s: &Series) -> PolarsResult<Option<Self>> {
+        Ok(s.str()?.get(0).map(str::to_string))
+    }
+}
+
+impl<'a, T: ScalarArg<'a>> ScalarArg<'a> for Option<T> {
+    fn from_scalar(s: &'a Series) -> PolarsResult<Option<Self>> {
+        Ok(T::from_scalar(s)?.map(Some))
+    }
+
+    fn from_null() -> Option<Self> {
+        Some(None)
+    }
+}
+
+/// Splits the inputs in the expression inputs and the last `n` inputs holding scalar arguments.
+#[doc(hidden)]
+pub fn _split_scalar_args(inputs: &[Series], n: usize) -> PolarsResult<(&[Series], &[Series])> {
+    polars_ensure!(
+        inputs.len() >= n,
+        InvalidOperation: "expected {} scalar arguments after the inputs, got {} inputs in total",
+        n, inputs.len()
+    );
+    Ok(inputs.split_at(inputs.len() - n))
+}
+
+/// Extracts the scalar argument `name`.
+#[doc(hidden)]
+pub fn _scalar_arg<'a, T: ScalarArg<'a>>(name: &str, s: &'a Series) -> PolarsResult<T> {
+    polars_ensure!(
+        s.len() == 1,
+        InvalidOperation: "argument `{}` must be a scalar, got a Series of length {}",
+        name, s.len()
+    );
+    // Keeps the kind of the error, e.g. `SchemaMismatch` for a string passed as a number.
+    let value = T::from_scalar(s).map_err(|err| {
+        err.wrap_msg(&|msg: &str| {
+            format!(
+                "invalid argument `{}` of dtype {}: {}",
+                name,
+                s.dtype(),
+                msg
+            )
+        })
+    })?;
+    match value {
+        Some(value) => Ok(value),
+        None => T::from_null()
+            .ok_or_else(|| polars_err!(InvalidOperation: "argument `{}` must not be null", name)),
+    }
+}
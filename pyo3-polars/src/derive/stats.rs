@@ -0,0 +1,293 @@
+//! Call statistics of expression functions, recorded with the `stats` feature.
+//!
+//! Every `#[polars_expr]` function counts its calls, the rows it received and returned, its wall
+//! time and its errors, panics included. The numbers are read from Python through the
+//! `_polars_plugin_stats` symbol, which returns them as a JSON array, and reset with
+//! `_polars_plugin_stats_reset`.
+//!
+//! With the `alloc-stats` feature, the
+//! [allocation statistics](crate::PolarsAllocator::stats) of a plugin using the
+//! [`PolarsAllocator`](crate::PolarsAllocator) are read in the same way through
+//! `_polars_plugin_allocator_stats` and reset with `_polars_plugin_allocator_stats_reset`.
+//!
+//! Without the features, the generated code records nothing and the symbols are not exported.
+#[cfg(any(feature = "stats", feature = "alloc-stats"))]
+use std::cell::RefCell;
+#[cfg(any(feature = "stats", feature = "alloc-stats"))]
+use std::ffi::CString;
+#[cfg(feature = "stats")]
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+#[cfg(feature = "stats")]
+use std::sync::Mutex;
+#[cfg(feature = "stats")]
+use std::time::Instant;
+
+use polars::prelude::*;
+
+/// The statistics of one expression function.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FunctionStats {
+    /// The name of the function.
+    pub name: String,
+    /// The number of calls.
+    pub calls: u64,
+    /// The number of calls that returned an error or panicked.
+    pub errors: u64,
+    /// The total length of the longest input of each call.
+    pub rows_in: u64,
+    /// The total length of the successful outputs.
+    pub rows_out: u64,
+    /// The total wall time of the calls, in nanoseconds.
+    pub wall_time_ns: u64,
+}
+
+/// The counters of an expression function, a `static` in the generated code.
+#[doc(hidden)]
+pub struct _CallStats {
+    #[cfg_attr(not(feature = "stats"), allow(dead_code))]
+    name: &'static str,
+    #[cfg(feature = "stats")]
+    registered: AtomicBool,
+    #[cfg(feature = "stats")]
+    calls: AtomicU64,
+    #[cfg(feature = "stats")]
+    errors: AtomicU64,
+    #[cfg(feature = "stats")]
+    rows_in: AtomicU64,
+    #[cfg(feature = "stats")]
+    rows_out: AtomicU64,
+    #[cfg(feature = "stats")]
+    wall_time_ns: AtomicU64,
+}
+
+#[cfg(feature = "stats")]
+static REGISTRY: Mutex<Vec<&'static _CallStats>> = Mutex::new(Vec::new());
+
+#[cfg(any(feature = "stats", feature = "alloc-stats"))]
+thread_local! {
+    static LAST_STATS: RefCell<CString> = RefCell::new(CString::default());
+}
+
+impl _CallStats {
+    #[doc(hidden)]
+    pub const fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            #[cfg(feature = "stats")]
+            registered: AtomicBool::new(false),
+            #[cfg(feature = "stats")]
+            calls: AtomicU64::new(0),
+            #[cfg(feature = "stats")]
+            errors: AtomicU64::new(0),
+            #[cfg(feature = "stats")]
+            rows_in: AtomicU64::new(0),
+            #[cfg(feature = "stats")]
+            rows_out: AtomicU64::new(0),
+            #[cfg(feature = "stats")]
+            wall_time_ns: AtomicU64::new(0),
+        }
+    }
+
+    /// Starts timing a call, its inputs are recorded with [`_CallTimer::record_inputs`].
+    #[doc(hidden)]
+    #[inline]
+    pub fn start(&'static self) -> _CallTimer {
+        #[cfg(feature = "stats")]
+        {
+            if !self.registered.swap(true, Ordering::AcqRel) {
+                REGISTRY.lock().unwrap().push(self);
+            }
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            _CallTimer {
+                stats: self,
+                start: Instant::now(),
+                finished: false,
+            }
+        }
+        #[cfg(not(feature = "stats"))]
+        _CallTimer {}
+    }
+
+    #[cfg(feature = "stats")]
+    fn snapshot(&self) -> FunctionStats {
+        FunctionStats {
+            name: self.name.to_string(),
+            calls: self.calls.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+            rows_in: self.rows_in.load(Ordering::Relaxed),
+            rows_out: self.rows_out.load(Ordering::Relaxed),
+            wall_time_ns: self.wall_time_ns.load(Ordering::Relaxed),
+        }
+    }
+
+    #[cfg(feature = "stats")]
+    fn reset(&self) {
+        for counter in [
+            &self.calls,
+            &self.errors,
+            &self.rows_in,
+            &self.rows_out,
+            &self.wall_time_ns,
+        ] {
+            counter.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+/// A running call. A timer dropped without [`_CallTimer::finish`] counts as an error: the call
+/// returned early, e.g. on invalid kwargs or a failed plugin initialization, or panicked.
+#[doc(hidden)]
+pub struct _CallTimer {
+    #[cfg(feature = "stats")]
+    stats: &'static _CallStats,
+    #[cfg(feature = "stats")]
+    start: Instant,
+    #[cfg(feature = "stats")]
+    finished: bool,
+}
+
+impl _CallTimer {
+    /// Records the inputs of the call, once the scalar arguments are split off.
+    #[doc(hidden)]
+    #[inline]
+    pub fn record_inputs(&self, inputs: &[Series]) {
+        #[cfg(feature = "stats")]
+        {
+            let rows_in = inputs.iter().map(|s| s.len()).max().unwrap_or(0);
+            self.stats
+                .rows_in
+                .fetch_add(rows_in as u64, Ordering::Relaxed);
+        }
+        #[cfg(not(feature = "stats"))]
+        let _ = inputs;
+    }
+
+    /// Records the result of the call.
+    #[doc(hidden)]
+    #[inline]
+    pub fn finish(self, result: &PolarsResult<Series>) {
+        #[cfg(feature = "stats")]
+        {
+            let mut this = self;
+            match result {
+                Ok(out) => this
+                    .stats
+                    .rows_out
+                    .fetch_add(out.len() as u64, Ordering::Relaxed),
+                Err(_) => this.stats.errors.fetch_add(1, Ordering::Relaxed),
+            };
+            this.record_time();
+            this.finished = true;
+        }
+        #[cfg(not(feature = "stats"))]
+        let _ = (self, result);
+    }
+
+    #[cfg(feature = "stats")]
+    fn record_time(&self) {
+        let elapsed = self.start.elapsed().as_nanos() as u64;
+        self.stats
+            .wall_time_ns
+            .fetch_add(elapsed, Ordering::Relaxed);
+    }
+}
+
+#[cfg(feature = "stats")]
+impl Drop for _CallTimer {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.stats.errors.fetch_add(1, Ordering::Relaxed);
+            self.record_time();
+        }
+    }
+}
+
+/// The statistics of the functions called so far, in the order of their first call.
+#[cfg(feature = "stats")]
+pub fn plugin_stats() -> Vec<FunctionStats> {
+    REGISTRY
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|stats| stats.snapshot())
+        .collect()
+}
+
+/// Resets the statistics of all functions.
+#[cfg(feature = "stats")]
+pub fn reset_plugin_stats() {
+    for stats in REGISTRY.lock().unwrap().iter() {
+        stats.reset()
+    }
+}
+
+#[cfg(feature = "stats")]
+#[no_mangle]
+/// Returns [`plugin_stats`] as a JSON array. The string is valid until the next call on this
+/// thread.
+///
+/// # Safety
+/// FFI function, so unsafe
+pub unsafe extern "C" fn _polars_plugin_stats() -> *const std::os::raw::c_char {
+    let stats = plugin_stats()
+        .into_iter()
+        .map(|stats| {
+            serde_json::json!({
+                "name": stats.name,
+                "calls": stats.calls,
+                "errors": stats.errors,
+                "rows_in": stats.rows_in,
+                "rows_out": stats.rows_out,
+                "wall_time_ns": stats.wall_time_ns,
+            })
+        })
+        .collect::<Vec<_>>();
+    let json = serde_json::Value::Array(stats).to_string();
+    LAST_STATS.with(|prev| {
+        *prev.borrow_mut() = CString::new(json).unwrap();
+        prev.borrow().as_ptr()
+    })
+}
+
+#[cfg(feature = "stats")]
+#[no_mangle]
+/// Calls [`reset_plugin_stats`].
+///
+/// # Safety
+/// FFI function, so unsafe
+pub unsafe extern "C" fn _polars_plugin_stats_reset() {
+    reset_plugin_stats()
+}
+
+#[cfg(feature = "alloc-stats")]
+#[no_mangle]
+/// Returns [`PolarsAllocator::stats`](crate::PolarsAllocator::stats) as a JSON object. The string
+/// is valid until the next call on this thread.
+///
+/// # Safety
+/// FFI function, so unsafe
+pub unsafe extern "C" fn _polars_plugin_allocator_stats() -> *const std::os::raw::c_char {
+    let stats = crate::PolarsAllocator::stats();
+    let json = serde_json::json!({
+        "live_bytes": stats.live_bytes,
+        "peak_bytes": stats.peak_bytes,
+        "allocations": stats.allocations,
+        "reallocations": stats.reallocations,
+        "frees": stats.frees,
+    })
+    .to_string();
+    LAST_STATS.with(|prev| {
+        *prev.borrow_mut() = CString::new(json).unwrap();
+        prev.borrow().as_ptr()
+    })
+}
+
+#[cfg(feature = "alloc-stats")]
+#[no_mangle]
+/// Calls [`PolarsAllocator::reset_stats`](crate::PolarsAllocator::reset_stats).
+///
+/// # Safety
+/// FFI function, so unsafe
+pub unsafe extern "C" fn _polars_plugin_allocator_stats_reset() {
+    crate::PolarsAllocator::reset_stats()
+}
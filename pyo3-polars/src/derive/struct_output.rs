@@ -0,0 +1,133 @@
+//! Return several columns from a `#[polars_expr]` function as a single `Struct` column.
+use polars::prelude::*;
+
+/// The output of a `#[polars_expr]` function that is returned as a `Struct` column.
+///
+/// Implemented for tuples of [`StructOutputField`]s, which get the fields `field_0`, `field_1`,
+/// etc., and with `#[derive(StructOutput)]` for structs with named fields:
+///
+/// ```rust,ignore
+/// #[derive(StructOutput)]
+/// struct WordStats {
+///     n_chars: UInt32Chunked,
+///     #[struct_output(dtype = Float64)]
+///     score: Series,
+/// }
+///
+/// #[polars_expr]
+/// fn word_stats(inputs: &[Series]) -> PolarsResult<WordStats> {
+///     ...
+/// }
+/// ```
+///
+/// The output type of the expression is derived from the member types. Members of which the dtype
+/// isn't known statically, e.g. a `Series` or a nested `ChunkedArray`, need the dtype given with
+/// `#[struct_output(dtype = ..)]`, and are cast to it. Such members can't be part of a tuple.
+pub trait StructOutput {
+    /// The fields of the struct, given the input fields of the expression.
+    fn struct_fields(input_fields: &[Field]) -> PolarsResult<Vec<Field>>;
+
+    /// The columns of the struct, named and ordered as in [`StructOutput::struct_fields`].
+    fn into_columns(self) -> PolarsResult<Vec<Series>>;
+}
+
+/// A member of a [`StructOutput`].
+pub trait StructOutputField {
+    /// The dtype of this member, if it is known without looking at the data.
+    fn static_dtype() -> Option<DataType>;
+
+    /// Convert this member into a column of the struct.
+    fn into_series(self) -> Series;
+}
+
+impl StructOutputField for Series {
+    fn static_dtype() -> Option<DataType> {
+        None
+    }
+
+    fn into_series(self) -> Series {
+        self
+    }
+}
+
+impl<T: PolarsDataType> StructOutputField for ChunkedArray<T>
+where
+    ChunkedArray<T>: IntoSeries,
+{
+    fn static_dtype() -> Option<DataType> {
+        // Nested types only know their outer type statically.
+        Some(T::get_dtype()).filter(|dtype| !dtype.is_nested())
+    }
+
+    fn into_series(self) -> Series {
+        IntoSeries::into_series(self)
+    }
+}
+
+#[doc(hidden)]
+pub fn _struct_output_field<T: StructOutputField>(
+    name: &str,
+    dtype: Option<DataType>,
+    _input_fields: &[Field],
+) -> PolarsResult<Field> {
+    let dtype = dtype.or_else(T::static_dtype).ok_or_else(|| {
+        polars_err!(
+            InvalidOperation: "the dtype of struct field '{}' isn't known before the call, give it with `#[struct_output(dtype = ..)]`",
+            name
+        )
+    })?;
+    Ok(Field::new(name.into(), dtype))
+}
+
+/// The output field of an expression returning `T`, named after the first input.
+#[doc(hidden)]
+pub fn _struct_output_dtype<T: StructOutput>(input_fields: &[Field]) -> PolarsResult<Field> {
+    let name = input_fields
+        .first()
+        .map(|field| field.name().clone())
+        .unwrap_or(PlSmallStr::EMPTY);
+    Ok(Field::new(
+        name,
+        DataType::Struct(T::struct_fields(input_fields)?),
+    ))
+}
+
+/// Build the `Struct` column of an expression returning `T`, named after the first input.
+#[doc(hidden)]
+pub fn _into_struct_series<T: StructOutput>(out: T, inputs: &[Series]) -> PolarsResult<Series> {
+    let name = inputs
+        .first()
+        .map(|s| s.name().clone())
+        .unwrap_or(PlSmallStr::EMPTY);
+    let columns = out.into_columns()?;
+    let length = columns.iter().map(|s| s.len()).max().unwrap_or(0);
+    let ca = StructChunked::from_series(name, length, columns.iter())?;
+    Ok(IntoSeries::into_series(ca))
+}
+
+macro_rules! impl_struct_output_tuple {
+    ($($idx:tt $T:ident),+) => {
+        impl<$($T: StructOutputField),+> StructOutput for ($($T,)+) {
+            fn struct_fields(input_fields: &[Field]) -> PolarsResult<Vec<Field>> {
+                Ok(vec![$(
+                    _struct_output_field::<$T>(concat!("field_", stringify!($idx)), None, input_fields)?
+                ),+])
+            }
+
+            fn into_columns(self) -> PolarsResult<Vec<Series>> {
+                Ok(vec![$(
+                    StructOutputField::into_series(self.$idx).with_name(concat!("field_", stringify!($idx)).into())
+                ),+])
+            }
+        }
+    };
+}
+
+impl_struct_output_tuple!(0 A);
+impl_struct_output_tuple!(0 A, 1 B);
+impl_struct_output_tuple!(0 A, 1 B, 2 C);
+impl_struct_output_tuple!(0 A, 1 B, 2 C, 3 D);
+impl_struct_output_tuple!(0 A, 1 B, 2 C, 3 D, 4 E);
+impl_struct_output_tuple!(0 A, 1 B, 2 C, 3 D, 4 E, 5 F);
+impl_struct_output_tuple!(0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G);
+impl_struct_output_tuple!(0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G, 7 H);
@@ -0,0 +1,253 @@
+//! Call the symbols generated by `#[polars_expr]` in-process, the way Polars does, so that plugins
+//! can be tested with `cargo test`.
+//!
+//! ```rust,ignore
+//! use pyo3_polars::derive::testing::{call_expr, call_field, pickle_kwargs};
+//!
+//! #[test]
+//! fn pig_latin() {
+//!     let names = Series::new("names".into(), ["Bob"]);
+//!     let kwargs = pickle_kwargs(&PigLatinKwargs { capitalize: false });
+//!     let out = unsafe {
+//!         call_expr(_polars_plugin_pig_latinnify, &[names], &kwargs, Default::default())
+//!     };
+//!     assert_eq!(out.unwrap().str().unwrap().get(0), Some("obBay"));
+//! }
+//! ```
+//!
+//! Failures are returned as the message Python would show, i.e. the last error of the plugin.
+use std::ffi::CStr;
+
+use polars::prelude::*;
+use polars_core::export::arrow::ffi::{export_field_to_c, import_field_from_c, ArrowSchema};
+use polars_ffi::version_0::{export_series, import_series, CallerContext, SeriesExport};
+use serde::Serialize;
+
+use super::{
+    _polars_plugin_clear_last_error, _polars_plugin_get_last_error_context,
+    _polars_plugin_get_last_error_kind, _polars_plugin_get_last_error_message, serialize_kwargs,
+    ErrorKind, KwargsEncoding,
+};
+
+/// The signature of `_polars_plugin_<name>`.
+pub type ExpressionSymbol = unsafe extern "C" fn(
+    *mut SeriesExport,
+    usize,
+    *const u8,
+    usize,
+    *mut SeriesExport,
+    *mut CallerContext,
+);
+
+/// The signature of `_polars_plugin_field_<name>`.
+pub type FieldSymbol =
+    unsafe extern "C" fn(*mut ArrowSchema, usize, *mut ArrowSchema, *const u8, usize);
+
+/// The signature of `_polars_plugin_context_field_<name>`.
+pub type ContextFieldSymbol = unsafe extern "C" fn(
+    *mut ArrowSchema,
+    usize,
+    *mut ArrowSchema,
+    *const u8,
+    usize,
+    *mut CallerContext,
+);
+
+/// The last error message of the plugin on this thread.
+pub fn last_error() -> String {
+    unsafe { CStr::from_ptr(_polars_plugin_get_last_error_message()) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// The kind of the last error of the plugin on this thread.
+pub fn last_error_kind() -> ErrorKind {
+    let code = unsafe { _polars_plugin_get_last_error_kind() };
+    ErrorKind::from_code(code).unwrap()
+}
+
+/// The context messages of the last error of the plugin on this thread, outermost first.
+pub fn last_error_context() -> Vec<String> {
+    let context = unsafe { CStr::from_ptr(_polars_plugin_get_last_error_context()) };
+    context
+        .to_string_lossy()
+        .lines()
+        .map(str::to_string)
+        .collect()
+}
+
+/// Pickles kwargs like `register_plugin_function` does.
+///
+/// # Panics
+/// If the kwargs can't be pickled.
+pub fn pickle_kwargs<T: Serialize>(kwargs: &T) -> Vec<u8> {
+    serialize_kwargs(kwargs, KwargsEncoding::Pickle).unwrap()
+}
+
+/// A `CallerContext` as Polars passes it when running the expression in parallel or not.
+pub fn caller_context(parallel: bool) -> CallerContext {
+    let mut context = CallerContext::default();
+    context._set_parallel(parallel);
+    context
+}
+
+/// Calls an expression symbol with the given inputs, serialized kwargs and context.
+///
+/// # Safety
+/// `symbol` must be generated by `#[polars_expr]` or follow the same contract.
+pub unsafe fn call_expr(
+    symbol: ExpressionSymbol,
+    inputs: &[Series],
+    kwargs: &[u8],
+    context: CallerContext,
+) -> Result<Series, String> {
+    call_expr_with(
+        symbol,
+        inputs,
+        kwargs,
+        context,
+        clear_last_error,
+        last_error,
+    )
+}
+
+/// Calls a field symbol with the given input fields and serialized kwargs.
+///
+/// # Safety
+/// `symbol` must be generated by `#[polars_expr]` or follow the same contract.
+pub unsafe fn call_field(
+    symbol: FieldSymbol,
+    fields: &[Field],
+    kwargs: &[u8],
+) -> Result<Field, String> {
+    call_field_with(symbol, fields, kwargs, clear_last_error, last_error)
+}
+
+/// Calls a context field symbol with the given input fields, serialized kwargs and context.
+///
+/// # Safety
+/// `symbol` must be generated by `#[polars_expr]` or follow the same contract.
+pub unsafe fn call_context_field(
+    symbol: ContextFieldSymbol,
+    fields: &[Field],
+    kwargs: &[u8],
+    context: CallerContext,
+) -> Result<Field, String> {
+    call_context_field_with(
+        symbol,
+        fields,
+        kwargs,
+        context,
+        clear_last_error,
+        last_error,
+    )
+}
+
+fn clear_last_error() {
+    unsafe { _polars_plugin_clear_last_error() }
+}
+
+/// The message of a failed call, which the plugin may not have set.
+fn failure(last_error: impl FnOnce() -> String) -> String {
+    let msg = last_error();
+    if msg.is_empty() {
+        "the plugin returned no output and set no error".to_string()
+    } else {
+        msg
+    }
+}
+
+/// Calls an expression symbol after `clear_last_error`, reading a failure with `last_error`.
+pub(crate) unsafe fn call_expr_with(
+    symbol: ExpressionSymbol,
+    inputs: &[Series],
+    kwargs: &[u8],
+    mut context: CallerContext,
+    clear_last_error: impl FnOnce(),
+    last_error: impl FnOnce() -> String,
+) -> Result<Series, String> {
+    clear_last_error();
+    let mut inputs = inputs.iter().map(export_series).collect::<Vec<_>>();
+    let mut return_value = SeriesExport::empty();
+    symbol(
+        inputs.as_mut_ptr(),
+        inputs.len(),
+        kwargs.as_ptr(),
+        kwargs.len(),
+        &mut return_value,
+        &mut context,
+    );
+    // The plugin owns the inputs once they are imported.
+    std::mem::forget(inputs);
+
+    if return_value.is_null() {
+        return Err(failure(last_error));
+    }
+    import_series(return_value).map_err(|err| err.to_string())
+}
+
+/// Calls a field symbol after `clear_last_error`, reading a failure with `last_error`.
+pub(crate) unsafe fn call_field_with(
+    symbol: FieldSymbol,
+    fields: &[Field],
+    kwargs: &[u8],
+    clear_last_error: impl FnOnce(),
+    last_error: impl FnOnce() -> String,
+) -> Result<Field, String> {
+    call_field_symbol(
+        |fields, len, return_value| {
+            symbol(fields, len, return_value, kwargs.as_ptr(), kwargs.len())
+        },
+        fields,
+        clear_last_error,
+        last_error,
+    )
+}
+
+/// Calls a context field symbol after `clear_last_error`, reading a failure with `last_error`.
+pub(crate) unsafe fn call_context_field_with(
+    symbol: ContextFieldSymbol,
+    fields: &[Field],
+    kwargs: &[u8],
+    mut context: CallerContext,
+    clear_last_error: impl FnOnce(),
+    last_error: impl FnOnce() -> String,
+) -> Result<Field, String> {
+    call_field_symbol(
+        |fields, len, return_value| {
+            symbol(
+                fields,
+                len,
+                return_value,
+                kwargs.as_ptr(),
+                kwargs.len(),
+                &mut context,
+            )
+        },
+        fields,
+        clear_last_error,
+        last_error,
+    )
+}
+
+/// Exports the fields and calls `symbol` with them and the return value.
+unsafe fn call_field_symbol(
+    symbol: impl FnOnce(*mut ArrowSchema, usize, *mut ArrowSchema),
+    fields: &[Field],
+    clear_last_error: impl FnOnce(),
+    last_error: impl FnOnce() -> String,
+) -> Result<Field, String> {
+    clear_last_error();
+    let mut fields = fields
+        .iter()
+        .map(|field| export_field_to_c(&field.to_arrow(CompatLevel::newest())))
+        .collect::<Vec<_>>();
+    let mut return_value = ArrowSchema::empty();
+    symbol(fields.as_mut_ptr(), fields.len(), &mut return_value);
+
+    if return_value.is_null() {
+        return Err(failure(last_error));
+    }
+    let field = import_field_from_c(&return_value).map_err(|err| err.to_string())?;
+    Ok(Field::from(&field))
+}
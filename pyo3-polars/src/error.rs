@@ -1,11 +1,15 @@
 #![allow(missing_docs)] // note - only for create_exception ... document the rest
 
-use std::fmt::{Debug, Formatter};
+use std::fmt::{Debug, Display, Formatter};
+use std::sync::Arc;
 
 use polars::prelude::PolarsError;
 use pyo3::create_exception;
-use pyo3::exceptions::{PyException, PyIOError, PyIndexError, PyRuntimeError, PyValueError};
+use pyo3::exceptions::{
+    PyException, PyIOError, PyIndexError, PyMemoryError, PyRuntimeError, PyValueError,
+};
 use pyo3::prelude::*;
+use pyo3::types::{PyList, PyType};
 use thiserror::Error;
 
 /// Error types for `pyo3-polars`
@@ -19,52 +23,188 @@ pub enum PyPolarsErr {
     Other(String),
 }
 
+impl From<PyErr> for PyPolarsErr {
+    fn from(err: PyErr) -> Self {
+        PyPolarsErr::Polars(to_polars_err(err))
+    }
+}
+
+/// A Python exception raised inside a Polars computation, e.g. by a Python callback.
+///
+/// It converts into a `PolarsError::IO` holding the exception, the only variant that can hold
+/// another error. When a [`PyPolarsErr`] holding it reaches Python, the original exception is
+/// raised again with its traceback, and the Polars context is added as notes.
+#[derive(Debug)]
+pub struct PythonError(pub PyErr);
+
+impl Display for PythonError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for PythonError {}
+
+impl From<PythonError> for PolarsError {
+    fn from(err: PythonError) -> Self {
+        PolarsError::IO {
+            error: Arc::new(std::io::Error::other(err)),
+            msg: None,
+        }
+    }
+}
+
+/// Wraps a Python exception in a `PolarsError`, see [`PythonError`].
+pub fn to_polars_err(err: PyErr) -> PolarsError {
+    PythonError(err).into()
+}
+
+/// The error `err` wraps with `PolarsError::context`, and the context messages, outermost first.
+pub(crate) fn context_chain(err: &PolarsError) -> (&PolarsError, Vec<String>) {
+    let mut context = vec![];
+    let mut err = err;
+    while let PolarsError::Context { error, msg } = err {
+        context.push(msg.to_string());
+        err = error;
+    }
+    (err, context)
+}
+
+/// Appends `note` to the `__notes__` of the exception, which Python 3.11 and newer show below
+/// the message.
+///
+/// A note the exception has already is skipped: a Python exception wrapped in a `PolarsError` is
+/// shared by its clones, so converting one twice would add its context again.
+fn add_note(py: Python<'_>, err: &PyErr, note: &str) {
+    let value = err.value(py);
+    let added = match value.getattr("__notes__") {
+        Ok(notes) if notes.contains(note).unwrap_or(false) => Ok(()),
+        Ok(notes) => notes.call_method1("append", (note,)).map(drop),
+        Err(_) => PyList::new(py, [note]).and_then(|notes| value.setattr("__notes__", notes)),
+    };
+    // Notes are informative, failing to add one shouldn't replace the error.
+    drop(added)
+}
+
+/// Adds `note` to `err`, e.g. which column was being converted.
+pub(crate) fn with_note(err: PyErr, note: &str) -> PyErr {
+    Python::with_gil(|py| add_note(py, &err, note));
+    err
+}
+
 impl std::convert::From<PyPolarsErr> for PyErr {
     fn from(err: PyPolarsErr) -> PyErr {
-        fn convert(err: &PolarsError) -> PyErr {
-            match err {
-                PolarsError::ComputeError(err) => ComputeError::new_err(err.to_string()),
-                PolarsError::NoData(err) => NoDataError::new_err(err.to_string()),
-                PolarsError::ShapeMismatch(err) => ShapeError::new_err(err.to_string()),
-                PolarsError::SchemaMismatch(err) => SchemaError::new_err(err.to_string()),
-                PolarsError::IO { error, .. } => PyIOError::new_err(error.to_string()),
-                PolarsError::OutOfBounds(err) => PyIndexError::new_err(err.to_string()),
-                PolarsError::InvalidOperation(err) => PyValueError::new_err(err.to_string()),
-                PolarsError::Duplicate(err) => DuplicateError::new_err(err.to_string()),
-                PolarsError::ColumnNotFound(err) => ColumnNotFound::new_err(err.to_string()),
-                PolarsError::SchemaFieldNotFound(err) => {
-                    SchemaFieldNotFound::new_err(err.to_string())
+        fn convert(py: Python<'_>, err: &PolarsError) -> PyErr {
+            let (name, fallback, msg) = match err {
+                PolarsError::ColumnNotFound(err) => {
+                    ("ColumnNotFoundError", py.get_type::<ColumnNotFound>(), err)
                 }
-                PolarsError::StructFieldNotFound(err) => {
-                    StructFieldNotFound::new_err(err.to_string())
+                PolarsError::ComputeError(err) => {
+                    ("ComputeError", py.get_type::<ComputeError>(), err)
                 }
-                PolarsError::StringCacheMismatch(err) => {
-                    StringCacheMismatchError::new_err(err.to_string())
+                PolarsError::Duplicate(err) => {
+                    ("DuplicateError", py.get_type::<DuplicateError>(), err)
                 }
-                PolarsError::SQLInterface(err) => SQLInterface::new_err(err.to_string()),
-                PolarsError::SQLSyntax(err) => SQLSyntax::new_err(err.to_string()),
-                PolarsError::Context { error, .. } => convert(error),
-            }
+                PolarsError::InvalidOperation(err) => (
+                    "InvalidOperationError",
+                    py.get_type::<InvalidOperationError>(),
+                    err,
+                ),
+                PolarsError::IO { error, msg } => {
+                    let err = match error
+                        .get_ref()
+                        .and_then(|e| e.downcast_ref::<PythonError>())
+                    {
+                        Some(PythonError(err)) => err.clone_ref(py),
+                        // E.g. an exceeded memory budget, see `with_memory_budget`.
+                        None if error.kind() == std::io::ErrorKind::OutOfMemory => {
+                            PyMemoryError::new_err(error.to_string())
+                        }
+                        // Polars raises the builtin `OSError` as well.
+                        None => PyIOError::new_err(error.to_string()),
+                    };
+                    if let Some(msg) = msg {
+                        add_note(py, &err, &msg.to_string());
+                    }
+                    return err;
+                }
+                PolarsError::NoData(err) => ("NoDataError", py.get_type::<NoDataError>(), err),
+                PolarsError::OutOfBounds(err) => {
+                    ("OutOfBoundsError", py.get_type::<OutOfBoundsError>(), err)
+                }
+                PolarsError::SchemaFieldNotFound(err) => (
+                    "SchemaFieldNotFoundError",
+                    py.get_type::<SchemaFieldNotFound>(),
+                    err,
+                ),
+                PolarsError::SchemaMismatch(err) => {
+                    ("SchemaError", py.get_type::<SchemaError>(), err)
+                }
+                PolarsError::ShapeMismatch(err) => ("ShapeError", py.get_type::<ShapeError>(), err),
+                PolarsError::SQLInterface(err) => {
+                    ("SQLInterfaceError", py.get_type::<SQLInterface>(), err)
+                }
+                PolarsError::SQLSyntax(err) => ("SQLSyntaxError", py.get_type::<SQLSyntax>(), err),
+                PolarsError::StringCacheMismatch(err) => (
+                    "StringCacheMismatchError",
+                    py.get_type::<StringCacheMismatchError>(),
+                    err,
+                ),
+                PolarsError::StructFieldNotFound(err) => (
+                    "StructFieldNotFoundError",
+                    py.get_type::<StructFieldNotFound>(),
+                    err,
+                ),
+                PolarsError::Context { .. } => {
+                    let (error, context) = context_chain(err);
+                    let err = convert(py, error);
+                    for msg in context.iter().rev() {
+                        add_note(py, &err, msg);
+                    }
+                    return err;
+                }
+            };
+            let ty = polars_exception(py, name).unwrap_or(fallback);
+            PyErr::from_type(ty, msg.to_string())
         }
 
         use PyPolarsErr::*;
         match &err {
-            Polars(err) => convert(err),
+            Polars(err) => Python::with_gil(|py| convert(py, err)),
             _ => PyRuntimeError::new_err(format!("{:?}", &err)),
         }
     }
 }
 
+/// The exception class `name` of `polars.exceptions`, if polars can be imported.
+fn polars_exception<'py>(py: Python<'py>, name: &str) -> Option<Bound<'py, PyType>> {
+    py.import("polars.exceptions")
+        .and_then(|module| module.getattr(name))
+        .ok()?
+        .downcast_into::<PyType>()
+        .ok()
+}
+
 impl Debug for PyPolarsErr {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         use PyPolarsErr::*;
         match self {
-            Polars(err) => write!(f, "{:?}", err),
+            Polars(err) => {
+                let (err, context) = context_chain(err);
+                write!(f, "{:?}", err)?;
+                for msg in context.iter().rev() {
+                    write!(f, "\n  context: {msg}")?;
+                }
+                Ok(())
+            }
             Other(err) => write!(f, "BindingsError: {:?}", err),
         }
     }
 }
 
+// Raised if `polars.exceptions` can't be imported, see `polars_exception`. Like the builtin
+// exceptions Python code would catch instead, an out of bounds index is an `IndexError` and an
+// invalid operation a `ValueError`.
 create_exception!(exceptions, ColumnNotFound, PyException);
 create_exception!(exceptions, SchemaFieldNotFound, PyException);
 create_exception!(exceptions, StructFieldNotFound, PyException);
@@ -76,3 +216,49 @@ create_exception!(exceptions, DuplicateError, PyException);
 create_exception!(exceptions, StringCacheMismatchError, PyException);
 create_exception!(exceptions, SQLInterface, PyException);
 create_exception!(exceptions, SQLSyntax, PyException);
+create_exception!(exceptions, InvalidOperationError, PyValueError);
+create_exception!(exceptions, OutOfBoundsError, PyIndexError);
+
+#[cfg(test)]
+mod tests {
+    use pyo3::exceptions::PyKeyError;
+
+    use super::*;
+
+    fn notes(err: &PyErr, py: Python<'_>) -> Vec<String> {
+        err.value(py)
+            .getattr("__notes__")
+            .unwrap()
+            .extract()
+            .unwrap()
+    }
+
+    #[test]
+    fn fallback_exceptions_subclass_builtin_ones() {
+        Python::with_gil(|py| {
+            let out_of_bounds = py.get_type::<OutOfBoundsError>();
+            assert!(out_of_bounds.is_subclass_of::<PyIndexError>().unwrap());
+            let invalid_operation = py.get_type::<InvalidOperationError>();
+            assert!(invalid_operation.is_subclass_of::<PyValueError>().unwrap());
+        });
+    }
+
+    #[test]
+    fn python_error_round_trips_through_polars_error() {
+        Python::with_gil(|py| {
+            let raised = py.run(c"{}['key']", None, None).unwrap_err();
+            let err = to_polars_err(raised.clone_ref(py)).context("looking up the key".into());
+            let back = PyErr::from(PyPolarsErr::from(err.clone()));
+
+            assert!(back.is_instance_of::<PyKeyError>(py));
+            assert!(back.value(py).is(raised.value(py)));
+            assert!(back.traceback(py).is_some());
+            assert_eq!(notes(&back, py), ["looking up the key"]);
+
+            // The clone shares the exception, converting it again doesn't repeat the note.
+            let again = PyErr::from(PyPolarsErr::from(err));
+            assert!(again.value(py).is(raised.value(py)));
+            assert_eq!(notes(&again, py), ["looking up the key"]);
+        });
+    }
+}
@@ -21,8 +21,13 @@ pub fn array_to_rust(obj: &Bound<PyAny>) -> PyResult<ArrayRef> {
     )?;
 
     unsafe {
-        let field = ffi::import_field_from_c(schema.as_ref()).map_err(PyPolarsErr::from)?;
-        let array = ffi::import_array_from_c(*array, field.dtype).map_err(PyPolarsErr::from)?;
+        let field = ffi::import_field_from_c(schema.as_ref())
+            .map_err(|err| err.context("importing the Arrow schema".into()))
+            .map_err(PyPolarsErr::from)?;
+        let dtype = format!("{:?}", field.dtype);
+        let array = ffi::import_array_from_c(*array, field.dtype)
+            .map_err(|err| err.context(format!("importing an Arrow array of type {dtype}").into()))
+            .map_err(PyPolarsErr::from)?;
         Ok(array)
     }
 }
@@ -49,14 +49,26 @@ pub mod error;
 #[cfg(feature = "derive")]
 pub mod export;
 mod ffi;
+#[cfg(feature = "loader")]
+pub mod loader;
+#[cfg(feature = "log")]
+pub mod logging;
+#[cfg(feature = "memory-budget")]
+pub mod memory_budget;
 mod types;
+pub mod warnings;
 
 use std::sync::LazyLock;
 
-pub use crate::alloc::PolarsAllocator;
+#[cfg(feature = "alloc-stats")]
+pub use crate::alloc::AllocatorStats;
+pub use crate::alloc::{AllocatorStatus, FallbackReason, PolarsAllocator};
+#[cfg(feature = "memory-budget")]
+pub use memory_budget::{check_memory_budget, memory_budget_exceeded, with_memory_budget};
 // use once_cell::sync::Lazy;
 use pyo3::prelude::*;
 pub use types::*;
+pub use warnings::{warn, PolarsWarning};
 
 pub(crate) static POLARS: LazyLock<Py<PyModule>> = LazyLock::new(|| {
     Python::with_gil(|py| {
@@ -0,0 +1,246 @@
+//! Load a compiled expression plugin and call its symbols from Rust, the way Polars does.
+//!
+//! ```rust,ignore
+//! use pyo3_polars::loader::Plugin;
+//!
+//! let plugin = unsafe { Plugin::load("target/release/libexpression_lib.so")? };
+//! for name in plugin.expressions() {
+//!     println!("{name}");
+//! }
+//! let names = Series::new("names".into(), ["Bob"]);
+//! let out = plugin.call("pig_latinnify", &[names], &[], Default::default())?;
+//! ```
+//!
+//! Kwargs are passed serialized, see [`serialize_kwargs`](crate::derive::serialize_kwargs).
+use std::ffi::{c_char, CStr};
+use std::path::{Path, PathBuf};
+
+use libloading::Library;
+use object::Object;
+use polars::prelude::*;
+use polars_core::error::to_compute_err;
+use polars_ffi::version_0::CallerContext;
+
+use crate::derive::testing::{
+    call_context_field_with, call_expr_with, call_field_with, ContextFieldSymbol, ExpressionSymbol,
+    FieldSymbol,
+};
+use crate::derive::ErrorKind;
+
+const PREFIX: &str = "_polars_plugin_";
+const FIELD_PREFIX: &str = "_polars_plugin_field_";
+const CONTEXT_FIELD_PREFIX: &str = "_polars_plugin_context_field_";
+
+/// A compiled plugin, loaded with `dlopen`.
+///
+/// The library is never unloaded, like polars does for its plugins: the `Series` and `Field`s it
+/// returns are released by callbacks in the library, which may run after the `Plugin` is dropped.
+pub struct Plugin {
+    library: &'static Library,
+    path: PathBuf,
+    version: (u16, u16),
+    symbols: Vec<String>,
+    expressions: Vec<String>,
+}
+
+impl Plugin {
+    /// Loads the shared library at `path` and checks that its FFI version is compatible with the
+    /// one of this crate.
+    ///
+    /// # Safety
+    /// Loading a library runs its initialization code. The library must be a plugin built with
+    /// `pyo3-polars`, whose `_polars_plugin_*` symbols follow the contract of `#[polars_expr]`.
+    pub unsafe fn load(path: impl AsRef<Path>) -> PolarsResult<Self> {
+        let path = path.as_ref().to_path_buf();
+        let symbols = plugin_symbols(&path)?;
+
+        let library: &'static Library =
+            Box::leak(Box::new(Library::new(&path).map_err(to_compute_err)?));
+        let get_version = library
+            .get::<unsafe extern "C" fn() -> u32>(b"_polars_plugin_get_version\0")
+            .map_err(|_| {
+                polars_err!(ComputeError: "{} is not a polars plugin: `_polars_plugin_get_version` is not exported", path.display())
+            })?;
+        let version = get_version();
+        let version = ((version >> 16) as u16, version as u16);
+        let (major, minor) = polars_ffi::get_version();
+        polars_ensure!(
+            version.0 == major && version.1 >= minor,
+            ComputeError: "plugin {} has FFI version {}.{}, expected {}.{}",
+            path.display(), version.0, version.1, major, minor
+        );
+
+        let expressions = symbols
+            .iter()
+            .filter_map(|symbol| symbol.strip_prefix(FIELD_PREFIX))
+            .filter(|name| {
+                symbols
+                    .iter()
+                    .any(|symbol| symbol.strip_prefix(PREFIX) == Some(*name))
+            })
+            .map(str::to_string)
+            .collect();
+
+        Ok(Plugin {
+            library,
+            path,
+            version,
+            symbols,
+            expressions,
+        })
+    }
+
+    /// The path the plugin was loaded from.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The FFI version of the plugin, as `(major, minor)`.
+    pub fn version(&self) -> (u16, u16) {
+        self.version
+    }
+
+    /// All exported `_polars_plugin_*` symbols, sorted.
+    pub fn symbols(&self) -> &[String] {
+        &self.symbols
+    }
+
+    /// The names of the expressions, i.e. of the symbols exported with an output type function.
+    pub fn expressions(&self) -> &[String] {
+        &self.expressions
+    }
+
+    /// Calls the expression `name` with the given inputs, serialized kwargs and context.
+    pub fn call(
+        &self,
+        name: &str,
+        inputs: &[Series],
+        kwargs: &[u8],
+        context: CallerContext,
+    ) -> PolarsResult<Series> {
+        let symbol = self.symbol::<ExpressionSymbol>(&format!("{PREFIX}{name}"))?;
+        unsafe {
+            call_expr_with(
+                symbol,
+                inputs,
+                kwargs,
+                context,
+                || self.clear_last_error(),
+                || self.last_error(),
+            )
+        }
+        .map_err(|msg| self.last_error_kind().into_error(msg))
+    }
+
+    /// Calls the output type function of the expression `name` with the given input fields and
+    /// serialized kwargs.
+    pub fn field(&self, name: &str, fields: &[Field], kwargs: &[u8]) -> PolarsResult<Field> {
+        let symbol = self.symbol::<FieldSymbol>(&format!("{FIELD_PREFIX}{name}"))?;
+        unsafe {
+            call_field_with(
+                symbol,
+                fields,
+                kwargs,
+                || self.clear_last_error(),
+                || self.last_error(),
+            )
+        }
+        .map_err(|msg| self.last_error_kind().into_error(msg))
+    }
+
+    /// Calls the output type function of the expression `name` with the given input fields,
+    /// serialized kwargs and context.
+    ///
+    /// Polars passes no context to output type functions, so plugins only take it from
+    /// `_polars_plugin_context_field_<name>`. Without that symbol, e.g. for a static
+    /// `output_type` or a plugin built before it existed, this is [`Plugin::field`].
+    pub fn field_with_context(
+        &self,
+        name: &str,
+        fields: &[Field],
+        kwargs: &[u8],
+        context: CallerContext,
+    ) -> PolarsResult<Field> {
+        let Ok(symbol) =
+            self.symbol::<ContextFieldSymbol>(&format!("{CONTEXT_FIELD_PREFIX}{name}"))
+        else {
+            return self.field(name, fields, kwargs);
+        };
+        unsafe {
+            call_context_field_with(
+                symbol,
+                fields,
+                kwargs,
+                context,
+                || self.clear_last_error(),
+                || self.last_error(),
+            )
+        }
+        .map_err(|msg| self.last_error_kind().into_error(msg))
+    }
+
+    /// Resets the last error of the plugin on this thread. Plugins built before the last error
+    /// could be reset keep it.
+    fn clear_last_error(&self) {
+        if let Ok(clear) = self.symbol::<unsafe extern "C" fn()>("_polars_plugin_clear_last_error")
+        {
+            unsafe { clear() }
+        }
+    }
+
+    /// The last error message of the plugin on this thread.
+    pub fn last_error(&self) -> String {
+        match self.symbol::<unsafe extern "C" fn() -> *const c_char>(
+            "_polars_plugin_get_last_error_message",
+        ) {
+            Ok(get_last_error) => unsafe { CStr::from_ptr(get_last_error()) }
+                .to_string_lossy()
+                .into_owned(),
+            Err(err) => err.to_string(),
+        }
+    }
+
+    /// The kind of the last error of the plugin on this thread. Plugins built before error kinds
+    /// were exported, or with kinds this version doesn't know, report a `ComputeError`.
+    pub fn last_error_kind(&self) -> ErrorKind {
+        self.symbol::<unsafe extern "C" fn() -> u32>("_polars_plugin_get_last_error_kind")
+            .ok()
+            .and_then(|get_kind| ErrorKind::from_code(unsafe { get_kind() }))
+            .unwrap_or(ErrorKind::ComputeError)
+    }
+
+    fn symbol<T: Copy>(&self, name: &str) -> PolarsResult<T> {
+        polars_ensure!(
+            self.symbols.iter().any(|symbol| symbol == name),
+            ComputeError: "plugin {} does not export `{}`", self.path.display(), name
+        );
+        let mut bytes = name.as_bytes().to_vec();
+        bytes.push(0);
+        // The library is never unloaded, so the symbol stays valid.
+        unsafe { self.library.get::<T>(&bytes) }
+            .map(|symbol| *symbol)
+            .map_err(to_compute_err)
+    }
+}
+
+/// Reads the exported `_polars_plugin_*` symbols from the object file.
+fn plugin_symbols(path: &Path) -> PolarsResult<Vec<String>> {
+    let data = std::fs::read(path)?;
+    let file = object::File::parse(&*data).map_err(to_compute_err)?;
+    let mut symbols = file
+        .exports()
+        .map_err(to_compute_err)?
+        .iter()
+        .filter_map(|export| std::str::from_utf8(export.name()).ok())
+        // Mach-O prefixes C symbols with an underscore.
+        .map(|name| match file.format() {
+            object::BinaryFormat::MachO => name.strip_prefix('_').unwrap_or(name),
+            _ => name,
+        })
+        .filter(|name| name.starts_with(PREFIX))
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+    symbols.sort();
+    symbols.dedup();
+    Ok(symbols)
+}
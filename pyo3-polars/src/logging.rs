@@ -0,0 +1,315 @@
+//! Route `log` records of a plugin to Python's `logging` module.
+//!
+//! Plugins run without the GIL, so records are buffered and forwarded to the logger named after
+//! their target (`expression_lib::expressions` -> `expression_lib.expressions`) the next time the
+//! interpreter runs Python code. With `POLARS_VERBOSE=1` they are written to stderr instead.
+//!
+//! Expression plugins install the logger on start up. `tracing` events reach it through the
+//! `log` feature of `tracing`.
+//!
+//! Records are then filtered by the Python loggers and handlers as usual. Records below `Warn` are
+//! dropped in Rust already, unless the level is lowered from Python with [`set_log_level`], which
+//! [`register`] adds to the module of the plugin together with [`flush_logs`]:
+//!
+//! ```rust,ignore
+//! #[pymodule]
+//! fn expression_lib(m: &Bound<PyModule>) -> PyResult<()> {
+//!     pyo3_polars::logging::register(m)
+//! }
+//! ```
+//!
+//! ```python
+//! import logging
+//! expression_lib.set_log_level(logging.DEBUG)
+//! ```
+use std::collections::VecDeque;
+use std::ffi::c_void;
+use std::os::raw::c_int;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard};
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use pyo3::ffi::{Py_AddPendingCall, Py_IsInitialized};
+use pyo3::prelude::*;
+
+/// Above this many buffered records, the oldest are dropped.
+const BUFFER_CAPACITY: usize = 4096;
+
+struct Buffered {
+    level: Level,
+    target: String,
+    message: String,
+}
+
+static BUFFER: Mutex<VecDeque<Buffered>> = Mutex::new(VecDeque::new());
+static DROPPED: AtomicUsize = AtomicUsize::new(0);
+static FLUSH_SCHEDULED: AtomicBool = AtomicBool::new(false);
+
+/// The buffer, also after a panic while it was locked: its records stay valid.
+fn buffer() -> MutexGuard<'static, VecDeque<Buffered>> {
+    BUFFER.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+/// Drops the oldest records above the capacity.
+fn cap(buffer: &mut VecDeque<Buffered>) {
+    while buffer.len() > BUFFER_CAPACITY {
+        buffer.pop_front();
+        DROPPED.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// The `log::Log` of plugins, see the module docs.
+pub struct PluginLogger;
+
+static LOGGER: PluginLogger = PluginLogger;
+
+/// Installs the [`PluginLogger`] as the logger of this library, with level `Warn`.
+///
+/// Does nothing if a logger was already installed.
+pub fn init() {
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(LevelFilter::Warn);
+    }
+}
+
+/// Installs the [`PluginLogger`], see [`init`], and adds [`flush_logs`] and [`set_log_level`] to
+/// the Python module of the plugin.
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    init();
+    m.add_function(wrap_pyfunction!(flush_logs, m)?)?;
+    m.add_function(wrap_pyfunction!(set_log_level, m)?)?;
+    Ok(())
+}
+
+fn verbose() -> bool {
+    std::env::var("POLARS_VERBOSE").as_deref().unwrap_or("") == "1"
+}
+
+impl Log for PluginLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        if verbose() {
+            eprintln!("[{} {}] {}", record.level(), record.target(), record.args());
+            return;
+        }
+
+        // Formatting runs user code, which may log or panic, so it happens before locking.
+        let record = Buffered {
+            level: record.level(),
+            target: record.target().to_string(),
+            message: record.args().to_string(),
+        };
+        {
+            let mut buffer = buffer();
+            buffer.push_back(record);
+            cap(&mut buffer);
+        }
+        schedule_flush();
+    }
+
+    fn flush(&self) {}
+}
+
+/// Asks the interpreter to flush the buffer the next time it runs Python code.
+fn schedule_flush() {
+    if unsafe { Py_IsInitialized() } == 0 || FLUSH_SCHEDULED.swap(true, Ordering::AcqRel) {
+        return;
+    }
+    // The pending call runs on the main thread, with the GIL.
+    if unsafe { Py_AddPendingCall(Some(pending_flush), std::ptr::null_mut()) } != 0 {
+        // The queue of pending calls is full, try again with the next record.
+        FLUSH_SCHEDULED.store(false, Ordering::Release);
+    }
+}
+
+extern "C" fn pending_flush(_arg: *mut c_void) -> c_int {
+    FLUSH_SCHEDULED.store(false, Ordering::Release);
+    // A failing handler shouldn't raise in unrelated Python code.
+    Python::with_gil(|py| {
+        if let Err(err) = flush_logs(py) {
+            err.write_unraisable(py, None);
+        }
+    });
+    0
+}
+
+/// Python's `logging` level of a `log` level.
+fn python_level(level: Level) -> u32 {
+    match level {
+        Level::Error => 40,
+        Level::Warn => 30,
+        Level::Info => 20,
+        Level::Debug => 10,
+        Level::Trace => 5,
+    }
+}
+
+/// Forwards the buffered records to Python's `logging` module.
+///
+/// If forwarding a record raises, e.g. in a filter, the error is returned and the records after it
+/// stay buffered for the next flush.
+#[pyfunction]
+pub fn flush_logs(py: Python<'_>) -> PyResult<()> {
+    let mut records = std::mem::take(&mut *buffer());
+    let result = forward(py, &mut records);
+    if !records.is_empty() {
+        // Put the remaining records back in front of the ones logged in the meantime.
+        {
+            let mut buffer = buffer();
+            records.append(&mut buffer);
+            cap(&mut records);
+            *buffer = records;
+        }
+        schedule_flush();
+    }
+    result
+}
+
+/// Forwards the records, removing each one before it is forwarded.
+fn forward(py: Python<'_>, records: &mut VecDeque<Buffered>) -> PyResult<()> {
+    let logging = py.import("logging")?;
+    let dropped = DROPPED.swap(0, Ordering::Relaxed);
+    if dropped > 0 {
+        let warned = logging
+            .getattr("getLogger")?
+            .call1(("pyo3_polars",))?
+            .call_method1(
+                "warning",
+                (format!("dropped {dropped} log records of the plugin"),),
+            );
+        if let Err(err) = warned {
+            DROPPED.fetch_add(dropped, Ordering::Relaxed);
+            return Err(err);
+        }
+    }
+    while let Some(record) = records.pop_front() {
+        let logger = logging
+            .getattr("getLogger")?
+            .call1((record.target.replace("::", "."),))?;
+        logger.call_method1("log", (python_level(record.level), record.message))?;
+    }
+    Ok(())
+}
+
+/// Sets the level of the records that are forwarded, from one of the levels of Python's
+/// `logging` module. `logging.NOTSET` forwards everything.
+#[pyfunction]
+pub fn set_log_level(level: u32) {
+    let filter = match level {
+        0..=5 => LevelFilter::Trace,
+        6..=10 => LevelFilter::Debug,
+        11..=20 => LevelFilter::Info,
+        21..=30 => LevelFilter::Warn,
+        31..=40 => LevelFilter::Error,
+        _ => LevelFilter::Off,
+    };
+    log::set_max_level(filter);
+}
+
+#[cfg(test)]
+mod tests {
+    use pyo3::types::PyModule;
+
+    use super::*;
+
+    /// Collects the records that reach Python's `logging`, and fails on the message "fail" of the
+    /// logger `expression_lib.failing`.
+    const CAPTURE: &std::ffi::CStr = cr#"
+import logging
+
+records = []
+
+class Capture(logging.Handler):
+    def emit(self, record):
+        records.append((record.name, record.levelno, record.getMessage()))
+
+def fail(record):
+    if record.getMessage() == "fail":
+        raise RuntimeError("filter failed")
+    return True
+
+logging.getLogger().addHandler(Capture())
+logging.getLogger().setLevel(logging.NOTSET)
+logging.getLogger("expression_lib.failing").addFilter(fail)
+"#;
+
+    type Records = Vec<(String, u32, String)>;
+
+    fn flushed_records(capture: &Bound<'_, PyModule>) -> PyResult<Records> {
+        flush_logs(capture.py())?;
+        captured(capture)
+    }
+
+    fn captured(capture: &Bound<'_, PyModule>) -> PyResult<Records> {
+        let records = capture.getattr("records")?;
+        let out = records.extract()?;
+        records.call_method0("clear")?;
+        Ok(out)
+    }
+
+    fn record(target: &str, level: u32, message: &str) -> (String, u32, String) {
+        (target.to_string(), level, message.to_string())
+    }
+
+    // One test, as the logger and its buffer are global.
+    #[test]
+    fn log_records_are_buffered_filtered_and_capped() -> PyResult<()> {
+        init();
+        Python::with_gil(|py| {
+            let capture = PyModule::from_code(py, CAPTURE, c"capture.py", c"capture")?;
+
+            log::warn!(target: "expression_lib::expressions", "overflowed");
+            assert!(captured(&capture)?.is_empty());
+            assert_eq!(
+                flushed_records(&capture)?,
+                [record("expression_lib.expressions", 30, "overflowed")]
+            );
+
+            log::info!(target: "expression_lib", "dropped below warn");
+            assert!(flushed_records(&capture)?.is_empty());
+
+            set_log_level(10);
+            log::debug!(target: "expression_lib", "forwarded");
+            log::trace!(target: "expression_lib", "dropped below debug");
+            assert_eq!(
+                flushed_records(&capture)?,
+                [record("expression_lib", 10, "forwarded")]
+            );
+            set_log_level(30);
+
+            for i in 0..4099 {
+                log::warn!(target: "expression_lib", "{i}");
+            }
+            let records = flushed_records(&capture)?;
+            assert_eq!(records.len(), 4097);
+            assert_eq!(
+                records[0],
+                record("pyo3_polars", 30, "dropped 3 log records of the plugin")
+            );
+            assert_eq!(records[1], record("expression_lib", 30, "3"));
+            assert_eq!(records[4096], record("expression_lib", 30, "4098"));
+
+            // The records after one that raised are kept for the next flush.
+            for message in ["before", "fail", "after"] {
+                log::warn!(target: "expression_lib::failing", "{message}");
+            }
+            let err = flush_logs(py).unwrap_err();
+            assert!(err.to_string().contains("filter failed"), "{err}");
+            assert_eq!(
+                captured(&capture)?,
+                [record("expression_lib.failing", 30, "before")]
+            );
+            assert_eq!(
+                flushed_records(&capture)?,
+                [record("expression_lib.failing", 30, "after")]
+            );
+            Ok(())
+        })
+    }
+}
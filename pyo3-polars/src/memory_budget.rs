@@ -0,0 +1,259 @@
+//! Scoped memory budgets, post-hoc accounting on the counters of the
+//! [`PolarsAllocator`](crate::PolarsAllocator).
+//!
+//! [`with_memory_budget`] runs a closure and fails with an out of memory `PolarsError::IO`, raised
+//! as `MemoryError` in Python, if the live bytes grew by more than the budget while it ran.
+//!
+//! A budget doesn't prevent the allocations that overrun it, it only detects them: on stable Rust a
+//! global allocator that fails aborts the process, so the allocator can't refuse them. Instead it
+//! marks the budget as exceeded once the live bytes pass it, and the computation has to stop
+//! itself: [`check_memory_budget`] fails once the budget of the calling thread is exceeded, and
+//! [`memory_budget_exceeded`] tells any thread, e.g. the workers of a parallel loop, that a running
+//! budget was exceeded. The closure's result is checked when it returns in any case. A single
+//! allocation larger than the memory left still brings the process down.
+//!
+//! `#[polars_expr(.., memory_budget = <bytes>)]` runs every call of an expression in a budget.
+//!
+//! The live bytes are those of the whole library, not of the scope: polars runs the work of a call
+//! on its thread pool, where allocations can't be attributed to the scope that caused them. So
+//! allocations of other threads count as well, and budgeted calls running at the same time see each
+//! other's allocations. Threads add their live bytes in batches of 64 KiB, so an overrun is seen up
+//! to that much per thread late. Choose budgets with headroom for both.
+//!
+//! The budget only sees allocations of a library whose global allocator is the `PolarsAllocator`.
+use std::cell::Cell;
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use polars::prelude::*;
+
+use crate::PolarsAllocator;
+
+/// The most budgeted scopes that can run at the same time, nested ones included.
+const MAX_SCOPES: usize = 64;
+
+/// A running budgeted scope, written on enter and exit, and by the allocator when it's exceeded.
+struct Slot {
+    /// The live bytes above which the scope is exceeded, `usize::MAX` if the slot is free.
+    threshold: AtomicUsize,
+    /// The highest live bytes above the threshold, 0 while the scope isn't exceeded.
+    peak: AtomicUsize,
+}
+
+#[allow(clippy::declare_interior_mutable_const)]
+const FREE: Slot = Slot {
+    threshold: AtomicUsize::new(usize::MAX),
+    peak: AtomicUsize::new(0),
+};
+
+static SLOTS: [Slot; MAX_SCOPES] = [FREE; MAX_SCOPES];
+/// The lowest threshold of the running scopes, so the allocator only scans the slots once one of
+/// them is exceeded.
+static THRESHOLD: AtomicUsize = AtomicUsize::new(usize::MAX);
+/// Held while claiming or freeing a slot, which updates `THRESHOLD`.
+static REGISTRY: Mutex<()> = Mutex::new(());
+
+thread_local! {
+    /// The innermost budgeted scope of this thread.
+    static CURRENT: Cell<Option<Budget>> = const { Cell::new(None) };
+}
+
+#[derive(Clone, Copy)]
+struct Budget {
+    slot: usize,
+    bytes: usize,
+    /// The live bytes when the scope started, which don't count against it.
+    baseline: usize,
+}
+
+/// Called by the allocator after the live bytes grew to `live`, must not allocate or lock.
+#[inline]
+pub(crate) fn record_live_bytes(live: usize) {
+    if live <= THRESHOLD.load(Ordering::Relaxed) {
+        return;
+    }
+    for slot in &SLOTS {
+        if live > slot.threshold.load(Ordering::Relaxed) {
+            slot.peak.fetch_max(live, Ordering::Relaxed);
+        }
+    }
+}
+
+fn update_threshold() {
+    let lowest = SLOTS
+        .iter()
+        .map(|slot| slot.threshold.load(Ordering::Relaxed))
+        .min()
+        .unwrap_or(usize::MAX);
+    THRESHOLD.store(lowest, Ordering::Relaxed);
+}
+
+struct Scope {
+    slot: usize,
+    previous: Option<Budget>,
+}
+
+impl Scope {
+    fn enter(bytes: usize) -> PolarsResult<Self> {
+        let baseline = PolarsAllocator::stats().live_bytes;
+        let _registry = REGISTRY.lock().unwrap_or_else(|err| err.into_inner());
+        let Some(slot) = SLOTS
+            .iter()
+            .position(|slot| slot.threshold.load(Ordering::Relaxed) == usize::MAX)
+        else {
+            polars_bail!(ComputeError: "more than {} memory budgets are running", MAX_SCOPES)
+        };
+        SLOTS[slot].peak.store(0, Ordering::Relaxed);
+        SLOTS[slot].threshold.store(
+            baseline.saturating_add(bytes).min(usize::MAX - 1),
+            Ordering::Relaxed,
+        );
+        update_threshold();
+
+        let budget = Budget {
+            slot,
+            bytes,
+            baseline,
+        };
+        Ok(Scope {
+            slot,
+            previous: CURRENT.with(|current| current.replace(Some(budget))),
+        })
+    }
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        CURRENT.with(|current| current.set(self.previous));
+        let _registry = REGISTRY.lock().unwrap_or_else(|err| err.into_inner());
+        SLOTS[self.slot]
+            .threshold
+            .store(usize::MAX, Ordering::Relaxed);
+        update_threshold();
+    }
+}
+
+fn budget_exceeded(budget: usize, used: usize) -> PolarsError {
+    PolarsError::IO {
+        error: Arc::new(io::Error::new(
+            io::ErrorKind::OutOfMemory,
+            format!("memory budget of {budget} bytes exceeded, {used} bytes were allocated"),
+        )),
+        msg: None,
+    }
+}
+
+/// Fails if the innermost [`with_memory_budget`] of this thread went over its budget so far.
+///
+/// Outside of a budgeted scope it always succeeds, threads of a pool can poll
+/// [`memory_budget_exceeded`] instead.
+pub fn check_memory_budget() -> PolarsResult<()> {
+    let Some(budget) = CURRENT.with(|current| current.get()) else {
+        return Ok(());
+    };
+    match SLOTS[budget.slot].peak.load(Ordering::Relaxed) {
+        0 => Ok(()),
+        peak => Err(budget_exceeded(budget.bytes, peak - budget.baseline)),
+    }
+}
+
+/// Whether a running [`with_memory_budget`] of any thread went over its budget, so that work running
+/// outside of the scope's thread, e.g. on a thread pool, can stop early.
+pub fn memory_budget_exceeded() -> bool {
+    THRESHOLD.load(Ordering::Relaxed) != usize::MAX
+        && SLOTS.iter().any(|slot| {
+            slot.threshold.load(Ordering::Relaxed) != usize::MAX
+                && slot.peak.load(Ordering::Relaxed) != 0
+        })
+}
+
+/// Runs `f`, failing if the live bytes grew by more than `bytes` while it ran, see the module
+/// docs.
+///
+/// If the budget was exceeded, its error is returned even if `f` failed, as going over the budget
+/// is the likely cause.
+///
+/// ```rust,ignore
+/// #[pyfunction]
+/// fn cross_join(left: PyDataFrame, right: PyDataFrame) -> PyResult<PyDataFrame> {
+///     let out = with_memory_budget(1 << 30, || left.0.cross_join(&right.0, None, None))
+///         .map_err(PyPolarsErr::from)?;
+///     Ok(PyDataFrame(out))
+/// }
+/// ```
+pub fn with_memory_budget<R>(bytes: usize, f: impl FnOnce() -> PolarsResult<R>) -> PolarsResult<R> {
+    let _scope = Scope::enter(bytes)?;
+    let out = f();
+    check_memory_budget()?;
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::alloc::{GlobalAlloc, Layout};
+
+    use super::*;
+    use crate::alloc::ALLOCATOR_TESTS;
+
+    #[test]
+    fn exceeded_budget_fails_the_call() {
+        let _lock = ALLOCATOR_TESTS.lock().unwrap();
+        let alloc = PolarsAllocator::new();
+        let layout = Layout::from_size_align(1 << 20, 8).unwrap();
+        let allocate = || unsafe {
+            alloc.dealloc(alloc.alloc(layout), layout);
+            Ok(())
+        };
+
+        with_memory_budget(2 << 20, allocate).unwrap();
+        let err = with_memory_budget(1024, allocate).unwrap_err();
+        assert!(
+            matches!(&err, PolarsError::IO { error, .. } if error.kind() == io::ErrorKind::OutOfMemory)
+        );
+        assert!(
+            err.to_string()
+                .contains("memory budget of 1024 bytes exceeded"),
+            "{err}"
+        );
+        // The earlier peak doesn't count against a new scope.
+        assert!(with_memory_budget(1024, check_memory_budget).is_ok());
+    }
+
+    #[test]
+    fn budget_is_marked_when_exceeded() {
+        let _lock = ALLOCATOR_TESTS.lock().unwrap();
+        let alloc = PolarsAllocator::new();
+        let layout = Layout::from_size_align(1 << 20, 8).unwrap();
+
+        let err = with_memory_budget(1024, || {
+            assert!(check_memory_budget().is_ok());
+            unsafe { alloc.dealloc(alloc.alloc(layout), layout) };
+            // Seen right away on the thread of the budget and on other threads.
+            assert!(check_memory_budget().is_err());
+            assert!(std::thread::scope(|s| s
+                .spawn(memory_budget_exceeded)
+                .join()
+                .unwrap()));
+            Ok(())
+        })
+        .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("memory budget of 1024 bytes exceeded"),
+            "{err}"
+        );
+        assert!(!memory_budget_exceeded());
+
+        // An exceeded inner budget doesn't fail the outer one.
+        let out = with_memory_budget(4 << 20, || {
+            let inner = with_memory_budget(1024, || unsafe {
+                alloc.dealloc(alloc.alloc(layout), layout);
+                Ok(())
+            });
+            assert!(inner.is_err());
+            check_memory_budget()
+        });
+        assert!(out.is_ok());
+    }
+}
@@ -1,6 +1,6 @@
 //! Polars type wrappers which implement `pyo3::FromPyObject` and `pyo3::IntoPyObject`
 use super::*;
-use crate::error::PyPolarsErr;
+use crate::error::{with_note, PyPolarsErr};
 use crate::ffi::to_py::to_py_array;
 use polars::export::arrow;
 use polars_core::datatypes::{CompatLevel, DataType};
@@ -180,20 +180,51 @@ impl<'a> FromPyObject<'a> for PySeries {
         let py_name = name.str()?;
         let name = py_name.to_cow()?;
 
-        let kwargs = PyDict::new(ob.py());
-        if let Ok(compat_level) = ob.call_method0("_newest_compat_level") {
-            let compat_level = compat_level.extract().unwrap();
-            let compat_level =
-                CompatLevel::with_level(compat_level).unwrap_or(CompatLevel::newest());
-            kwargs.set_item("compat_level", compat_level.get_level())?;
-        }
-        let arr = ob.call_method("to_arrow", (), Some(&kwargs))?;
-        let arr = ffi::to_rust::array_to_rust(&arr)?;
-        let name = name.as_ref();
-        Ok(PySeries(
-            Series::try_from((PlSmallStr::from(name), arr)).map_err(PyPolarsErr::from)?,
-        ))
+        series_from_python(&ob, &name).map_err(|err| {
+            let dtype = ob
+                .getattr("dtype")
+                .and_then(|dtype| dtype.str())
+                .map(|dtype| dtype.to_string())
+                .unwrap_or_else(|_| "unknown".to_string());
+            with_note(
+                err,
+                &format!("while converting column `{name}` of dtype {dtype} from Python to Rust"),
+            )
+        })
+    }
+}
+
+fn series_from_python(ob: &Bound<'_, PyAny>, name: &str) -> PyResult<PySeries> {
+    let kwargs = PyDict::new(ob.py());
+    if let Ok(compat_level) = ob.call_method0("_newest_compat_level") {
+        let compat_level = compat_level.extract().unwrap();
+        let compat_level = CompatLevel::with_level(compat_level).unwrap_or(CompatLevel::newest());
+        kwargs.set_item("compat_level", compat_level.get_level())?;
     }
+    let arr = ob.call_method("to_arrow", (), Some(&kwargs))?;
+    let arr = ffi::to_rust::array_to_rust(&arr)?;
+    let s = Series::try_from((PlSmallStr::from(name), arr.clone())).map_err(|err| {
+        match failing_field_path(name, &arr) {
+            Some(path) => err.context(format!("in struct field `{path}`").into()),
+            None => err,
+        }
+    });
+    Ok(PySeries(s.map_err(PyPolarsErr::from)?))
+}
+
+/// The path of the innermost struct field of `arr` that can't be converted, e.g. `column.a.b`.
+fn failing_field_path(name: &str, arr: &ArrayRef) -> Option<String> {
+    let ArrowDataType::Struct(fields) = arr.dtype().to_logical_type() else {
+        return None;
+    };
+    let arr = arr.as_any().downcast_ref::<arrow::array::StructArray>()?;
+    fields.iter().zip(arr.values()).find_map(|(field, values)| {
+        if Series::try_from((field.name.clone(), values.clone())).is_ok() {
+            return None;
+        }
+        let path = format!("{name}.{}", field.name);
+        Some(failing_field_path(&path, values).unwrap_or(path))
+    })
 }
 
 impl<'a> FromPyObject<'a> for PyDataFrame {
@@ -245,74 +276,85 @@ impl<'py> IntoPyObject<'py> for PySeries {
     type Output = Bound<'py, Self::Target>;
     type Error = PyErr;
     fn into_pyobject(self, py: Python<'py>) -> Result<Self::Output, Self::Error> {
-        let polars = POLARS.bind(py);
-        let s = SERIES.bind(py);
-        match s
-            .getattr("_import_arrow_from_c")
-            .or_else(|_| s.getattr("_import_from_c"))
-        {
-            // Go via polars
-            Ok(import_arrow_from_c) => {
-                // Get supported compatibility level
-                let compat_level = CompatLevel::with_level(
-                    s.getattr("_newest_compat_level")
-                        .map_or(1, |newest_compat_level| {
-                            newest_compat_level.call0().unwrap().extract().unwrap()
-                        }),
-                )
-                .unwrap_or(CompatLevel::newest());
-                // Prepare pointers on the heap.
-                let mut chunk_ptrs = Vec::with_capacity(self.0.n_chunks());
-                for i in 0..self.0.n_chunks() {
-                    let array = self.0.to_arrow(i, compat_level);
-                    let schema = Box::new(arrow::ffi::export_field_to_c(&ArrowField::new(
-                        "".into(),
-                        array.dtype().clone(),
-                        true,
-                    )));
-                    let array = Box::new(arrow::ffi::export_array_to_c(array.clone()));
-
-                    let schema_ptr: *const arrow::ffi::ArrowSchema = Box::leak(schema);
-                    let array_ptr: *const arrow::ffi::ArrowArray = Box::leak(array);
-
-                    chunk_ptrs.push((schema_ptr as Py_uintptr_t, array_ptr as Py_uintptr_t))
-                }
-
-                // Somehow we need to clone the Vec, because pyo3 doesn't accept a slice here.
-                let pyseries =
-                    import_arrow_from_c.call1((self.0.name().as_str(), chunk_ptrs.clone()))?;
-                // Deallocate boxes
-                for (schema_ptr, array_ptr) in chunk_ptrs {
-                    let schema_ptr = schema_ptr as *mut arrow::ffi::ArrowSchema;
-                    let array_ptr = array_ptr as *mut arrow::ffi::ArrowArray;
-                    unsafe {
-                        // We can drop both because the `schema` isn't read in an owned matter on the other side.
-                        let _ = Box::from_raw(schema_ptr);
-
-                        // The array is `ptr::read_unaligned` so there are two owners.
-                        // We drop the box, and forget the content so the other process is the owner.
-                        let array = Box::from_raw(array_ptr);
-                        // We must forget because the other process will call the release callback.
-                        // Read *array as Box::into_inner
-                        let array = *array;
-                        std::mem::forget(array);
-                    }
-                }
+        let name = self.0.name().clone();
+        let dtype = self.0.dtype().clone();
+        series_to_python(py, self.0).map_err(|err| {
+            with_note(
+                err,
+                &format!("while converting column `{name}` of dtype {dtype} from Rust to Python"),
+            )
+        })
+    }
+}
 
-                Ok(pyseries)
+fn series_to_python(py: Python<'_>, series: Series) -> PyResult<Bound<'_, PyAny>> {
+    let polars = POLARS.bind(py);
+    let s = SERIES.bind(py);
+    match s
+        .getattr("_import_arrow_from_c")
+        .or_else(|_| s.getattr("_import_from_c"))
+    {
+        // Go via polars
+        Ok(import_arrow_from_c) => {
+            // Get supported compatibility level
+            let compat_level = CompatLevel::with_level(
+                s.getattr("_newest_compat_level")
+                    .map_or(1, |newest_compat_level| {
+                        newest_compat_level.call0().unwrap().extract().unwrap()
+                    }),
+            )
+            .unwrap_or(CompatLevel::newest());
+            // Prepare pointers on the heap.
+            let mut chunk_ptrs = Vec::with_capacity(series.n_chunks());
+            for i in 0..series.n_chunks() {
+                let array = series.to_arrow(i, compat_level);
+                let schema = Box::new(arrow::ffi::export_field_to_c(&ArrowField::new(
+                    "".into(),
+                    array.dtype().clone(),
+                    true,
+                )));
+                let array = Box::new(arrow::ffi::export_array_to_c(array.clone()));
+
+                let schema_ptr: *const arrow::ffi::ArrowSchema = Box::leak(schema);
+                let array_ptr: *const arrow::ffi::ArrowArray = Box::leak(array);
+
+                chunk_ptrs.push((schema_ptr as Py_uintptr_t, array_ptr as Py_uintptr_t))
             }
-            // Go via pyarrow
-            Err(_) => {
-                let s = self.0.rechunk();
-                let name = s.name().as_str();
-                let arr = s.to_arrow(0, CompatLevel::oldest());
-                let pyarrow = py.import("pyarrow").expect("pyarrow not installed");
-
-                let arg = to_py_array(arr, pyarrow)?;
-                let s = polars.call_method1("from_arrow", (arg,))?;
-                let s = s.call_method1("rename", (name,))?;
-                Ok(s)
+
+            // Somehow we need to clone the Vec, because pyo3 doesn't accept a slice here.
+            let pyseries =
+                import_arrow_from_c.call1((series.name().as_str(), chunk_ptrs.clone()))?;
+            // Deallocate boxes
+            for (schema_ptr, array_ptr) in chunk_ptrs {
+                let schema_ptr = schema_ptr as *mut arrow::ffi::ArrowSchema;
+                let array_ptr = array_ptr as *mut arrow::ffi::ArrowArray;
+                unsafe {
+                    // We can drop both because the `schema` isn't read in an owned matter on the other side.
+                    let _ = Box::from_raw(schema_ptr);
+
+                    // The array is `ptr::read_unaligned` so there are two owners.
+                    // We drop the box, and forget the content so the other process is the owner.
+                    let array = Box::from_raw(array_ptr);
+                    // We must forget because the other process will call the release callback.
+                    // Read *array as Box::into_inner
+                    let array = *array;
+                    std::mem::forget(array);
+                }
             }
+
+            Ok(pyseries)
+        }
+        // Go via pyarrow
+        Err(_) => {
+            let s = series.rechunk();
+            let name = s.name().as_str();
+            let arr = s.to_arrow(0, CompatLevel::oldest());
+            let pyarrow = py.import("pyarrow").expect("pyarrow not installed");
+
+            let arg = to_py_array(arr, pyarrow)?;
+            let s = polars.call_method1("from_arrow", (arg,))?;
+            let s = s.call_method1("rename", (name,))?;
+            Ok(s)
         }
     }
 }
@@ -543,7 +585,15 @@ impl<'py> IntoPyObject<'py> for PySchema {
     fn into_pyobject(self, py: Python<'py>) -> Result<Self::Output, Self::Error> {
         let dict = PyDict::new(py);
         for (k, v) in self.0.iter() {
-            dict.set_item(k.as_str(), PyDataType(v.clone()))?;
+            dict.set_item(k.as_str(), PyDataType(v.clone()))
+                .map_err(|err| {
+                    with_note(
+                        err,
+                        &format!(
+                            "while converting the dtype {v} of column `{k}` from Rust to Python"
+                        ),
+                    )
+                })?;
         }
         Ok(dict)
     }
@@ -674,10 +724,18 @@ impl<'py> FromPyObject<'py> for PyDataType {
             "Struct" => {
                 let fields = ob.getattr(intern!(py, "fields"))?;
                 let fields = fields
-                    .extract::<Vec<PyField>>()?
-                    .into_iter()
-                    .map(|f| f.0)
-                    .collect::<Vec<Field>>();
+                    .try_iter()?
+                    .map(|field| {
+                        let field = field?;
+                        field.extract::<PyField>().map(|f| f.0).map_err(|err| {
+                            let name = field
+                                .getattr(intern!(py, "name"))
+                                .map(|name| name.to_string())
+                                .unwrap_or_default();
+                            with_note(err, &format!("in struct field `{name}`"))
+                        })
+                    })
+                    .collect::<PyResult<Vec<Field>>>()?;
                 DataType::Struct(fields)
             },
             "Null" => DataType::Null,
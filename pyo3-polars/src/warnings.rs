@@ -0,0 +1,202 @@
+//! Emit the warnings of `polars.exceptions` from Rust.
+//!
+//! In a `#[pyfunction]`, [`warn`] calls `warnings.warn` right away. Expression plugins run without
+//! the GIL, so inside a plugin call [`warn`] buffers the warning on the calling thread. When the
+//! call returns, the buffer is moved to the queue of the plugin, which the host drains through the
+//! `_polars_plugin_take_warnings` symbol, e.g. with `ctypes` after `collect()`, to emit them with
+//! `warnings.warn`. If the host never drains it, the queue keeps the last 4096 warnings.
+#[cfg(feature = "derive")]
+use std::cell::RefCell;
+#[cfg(feature = "derive")]
+use std::collections::VecDeque;
+use std::ffi::CString;
+#[cfg(feature = "derive")]
+use std::sync::Mutex;
+
+use pyo3::exceptions::PyUserWarning;
+use pyo3::prelude::*;
+use pyo3::types::PyType;
+
+/// A warning class of `polars.exceptions`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PolarsWarning {
+    /// `PolarsWarning`, the base class of the others.
+    Polars,
+    /// `PerformanceWarning`
+    Performance,
+    /// `CategoricalRemappingWarning`
+    CategoricalRemapping,
+    /// `ChronoFormatWarning`
+    ChronoFormat,
+    /// `CustomUFuncWarning`
+    CustomUFunc,
+    /// `DataOrientationWarning`
+    DataOrientation,
+    /// `MapWithoutReturnDtypeWarning`
+    MapWithoutReturnDtype,
+    /// `UnstableWarning`
+    Unstable,
+}
+
+impl PolarsWarning {
+    /// The name of the class in `polars.exceptions`.
+    pub fn class_name(self) -> &'static str {
+        match self {
+            Self::Polars => "PolarsWarning",
+            Self::Performance => "PerformanceWarning",
+            Self::CategoricalRemapping => "CategoricalRemappingWarning",
+            Self::ChronoFormat => "ChronoFormatWarning",
+            Self::CustomUFunc => "CustomUFuncWarning",
+            Self::DataOrientation => "DataOrientationWarning",
+            Self::MapWithoutReturnDtype => "MapWithoutReturnDtypeWarning",
+            Self::Unstable => "UnstableWarning",
+        }
+    }
+
+    /// The class in `polars.exceptions`, or `UserWarning` if polars can't be imported.
+    pub fn class<'py>(self, py: Python<'py>) -> Bound<'py, PyType> {
+        py.import("polars.exceptions")
+            .and_then(|module| module.getattr(self.class_name()))
+            .ok()
+            .and_then(|class| class.downcast_into::<PyType>().ok())
+            .unwrap_or_else(|| py.get_type::<PyUserWarning>())
+    }
+}
+
+/// A warning buffered by a plugin.
+#[cfg(feature = "derive")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BufferedWarning {
+    /// The class of the warning.
+    pub kind: PolarsWarning,
+    /// The message of the warning.
+    pub message: String,
+}
+
+#[cfg(feature = "derive")]
+thread_local! {
+    static CALL_WARNINGS: RefCell<Vec<BufferedWarning>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Above this many queued warnings, the oldest are dropped.
+#[cfg(feature = "derive")]
+const QUEUE_CAPACITY: usize = 4096;
+
+#[cfg(feature = "derive")]
+struct Queue {
+    warnings: VecDeque<BufferedWarning>,
+    dropped: usize,
+}
+
+#[cfg(feature = "derive")]
+static PLUGIN_WARNINGS: Mutex<Queue> = Mutex::new(Queue {
+    warnings: VecDeque::new(),
+    dropped: 0,
+});
+
+/// Emits a Polars warning.
+///
+/// Inside an expression plugin call the warning is buffered, see the module docs. Otherwise it is
+/// passed to `warnings.warn`, which raises if the warning filters turn it into an error.
+pub fn warn(kind: PolarsWarning, message: impl Into<String>) -> PyResult<()> {
+    let message = message.into();
+    #[cfg(feature = "derive")]
+    if crate::derive::in_plugin_call() {
+        CALL_WARNINGS.with(|warnings| {
+            warnings
+                .borrow_mut()
+                .push(BufferedWarning { kind, message })
+        });
+        return Ok(());
+    }
+    Python::with_gil(|py| {
+        let message = CString::new(message)?;
+        PyErr::warn(py, kind.class(py).as_any(), &message, 1)
+    })
+}
+
+/// Moves the warnings of the finished plugin call to the queue of the plugin.
+#[cfg(feature = "derive")]
+pub(crate) fn flush_call_warnings() {
+    let warnings = CALL_WARNINGS.with(|warnings| std::mem::take(&mut *warnings.borrow_mut()));
+    if warnings.is_empty() {
+        return;
+    }
+    let mut queue = PLUGIN_WARNINGS.lock().unwrap();
+    for warning in warnings {
+        if queue.warnings.len() >= QUEUE_CAPACITY {
+            queue.warnings.pop_front();
+            queue.dropped += 1;
+        }
+        queue.warnings.push_back(warning);
+    }
+}
+
+/// Takes the warnings buffered by plugin calls, in the order the calls finished.
+///
+/// If warnings were dropped from the full queue, a `PolarsWarning` saying how many comes first.
+#[cfg(feature = "derive")]
+pub fn take_plugin_warnings() -> Vec<BufferedWarning> {
+    let mut queue = PLUGIN_WARNINGS.lock().unwrap();
+    let dropped = std::mem::take(&mut queue.dropped);
+    let mut warnings = Vec::with_capacity(queue.warnings.len() + 1);
+    if dropped > 0 {
+        warnings.push(BufferedWarning {
+            kind: PolarsWarning::Polars,
+            message: format!("dropped {dropped} warnings of the plugin"),
+        });
+    }
+    warnings.extend(queue.warnings.drain(..));
+    warnings
+}
+
+#[cfg(feature = "derive")]
+thread_local! {
+    static LAST_WARNINGS: RefCell<CString> = RefCell::new(CString::default());
+}
+
+#[cfg(feature = "derive")]
+#[no_mangle]
+/// Takes the buffered warnings as a JSON array of `{"kind": <class name>, "message": ..}`. The
+/// string is valid until the next call on this thread.
+///
+/// # Safety
+/// FFI function, so unsafe
+pub unsafe extern "C" fn _polars_plugin_take_warnings() -> *const std::os::raw::c_char {
+    let warnings = take_plugin_warnings()
+        .into_iter()
+        .map(|warning| {
+            format!(
+                r#"{{"kind":{},"message":{}}}"#,
+                json_string(warning.kind.class_name()),
+                json_string(&warning.message)
+            )
+        })
+        .collect::<Vec<_>>();
+    let json = format!("[{}]", warnings.join(","));
+    LAST_WARNINGS.with(|prev| {
+        // JSON escapes nul bytes, so `CString::new` can't fail.
+        *prev.borrow_mut() = CString::new(json).unwrap();
+        prev.borrow().as_ptr()
+    })
+}
+
+/// Quotes `s` as a JSON string, escaping control characters, so plugins don't need a JSON library.
+#[cfg(feature = "derive")]
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c < ' ' => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}